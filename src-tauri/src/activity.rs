@@ -0,0 +1,346 @@
+// 活动动态模块
+// 从 gateway 日志流中识别 bot 正在做的事情：收到的消息、发出的回复、工具调用（含耗时）和 LLM 请求错误，
+// 以 activity 事件推送给前端，并保留最近的历史供仪表盘查询
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{Emitter, State};
+
+use crate::logger;
+use crate::logparse::{self, LogLevel, LogRecord};
+use crate::logredact;
+use crate::logstream::{LogStreamHub, StreamOptions};
+
+/// 保留的历史条数
+const MAX_HISTORY: usize = 500;
+
+/// 消息预览的最大字符数
+const PREVIEW_CHARS: usize = 200;
+
+/// 启动时从日志末尾回填历史读取的字节数
+const BACKFILL_BYTES: u64 = 256 * 1024;
+
+/// 活动订阅的合并间隔（毫秒）
+const ACTIVITY_BATCH_MS: u64 = 200;
+
+/// 活动类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Activity {
+    /// 收到消息
+    #[serde(rename_all = "camelCase")]
+    InboundMessage { channel: String, sender: String, preview: String },
+    /// 发出回复
+    #[serde(rename_all = "camelCase")]
+    OutboundReply { channel: String, recipient: String, preview: String },
+    /// 工具调用，耗时为日志给出的时间或到下一条 agent 日志的间隔
+    #[serde(rename_all = "camelCase")]
+    ToolCall { tool: String, arguments: String, duration_ms: Option<u64> },
+    /// LLM 请求错误
+    #[serde(rename_all = "camelCase")]
+    LlmError { provider: Option<String>, model: Option<String>, message: String },
+}
+
+impl Activity {
+    fn kind(&self) -> &'static str {
+        match self {
+            Activity::InboundMessage { .. } => "inboundMessage",
+            Activity::OutboundReply { .. } => "outboundReply",
+            Activity::ToolCall { .. } => "toolCall",
+            Activity::LlmError { .. } => "llmError",
+        }
+    }
+}
+
+/// 一条活动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEvent {
+    pub id: u64,
+    pub timestamp: Option<String>,
+    #[serde(flatten)]
+    pub activity: Activity,
+}
+
+struct Patterns {
+    inbound: Regex,
+    outbound: Regex,
+    tool_call: Regex,
+    duration: Regex,
+    llm: Regex,
+    model: Regex,
+    provider_module: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| Patterns {
+        // "Processing message from telegram:12345: hello"
+        inbound: Regex::new(r"(?i)\bmessage from ([\w\-]+):(\S+?):\s?(.*)$").unwrap(),
+        // "Response to telegram:12345: hi"
+        outbound: Regex::new(r"(?i)\b(?:response|reply|sending (?:message|reply)) to ([\w\-]+):(\S+?):\s?(.*)$").unwrap(),
+        // "Tool call: web_search({"query": "..."})"
+        tool_call: Regex::new(r"(?i)\b(?:tool call|executing tool|calling tool):\s*([\w.\-]+)\s*(?:\((.*))?$").unwrap(),
+        // "took 1.5s"、"completed in 120ms"
+        duration: Regex::new(r"(?i)\b(?:took|in)\s+(\d+(?:\.\d+)?)\s*(ms|s)\b").unwrap(),
+        llm: Regex::new(r"(?i)\b(llm|litellm|completion|provider|openai|anthropic|openrouter|deepseek|gemini|rate limit|api error|model)\b").unwrap(),
+        model: Regex::new(r#"(?i)\bmodel\b\s*[=:]?\s*['"]?([\w./:\-]+)"#).unwrap(),
+        provider_module: Regex::new(r"providers\.(\w+)").unwrap(),
+    })
+}
+
+/// 截断为预览
+fn preview(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() > PREVIEW_CHARS {
+        let cut: String = text.chars().take(PREVIEW_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        text.to_string()
+    }
+}
+
+/// 解析 loguru 时间戳
+fn parse_timestamp(timestamp: Option<&str>) -> Option<chrono::NaiveDateTime> {
+    let timestamp = timestamp?.replacen('T', " ", 1);
+    chrono::NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+/// 是否为 agent 主循环的日志（用于结束正在进行的工具调用）
+fn is_agent_record(record: &LogRecord) -> bool {
+    record.module.as_deref().map(|m| m.starts_with("nanobot.agent")).unwrap_or(false)
+}
+
+/// 增量活动解析器
+#[derive(Debug, Default)]
+pub struct ActivityParser {
+    next_id: u64,
+    /// 尚未结束的工具调用
+    pending_tool: Option<ActivityEvent>,
+}
+
+impl ActivityParser {
+    pub fn new() -> Self {
+        Self { next_id: 1, pending_tool: None }
+    }
+
+    fn event(&mut self, record: &LogRecord, activity: Activity) -> ActivityEvent {
+        let id = self.next_id;
+        self.next_id += 1;
+        ActivityEvent {
+            id,
+            timestamp: record.timestamp.clone(),
+            activity,
+        }
+    }
+
+    /// 用当前记录结束正在进行的工具调用
+    fn finish_tool(&mut self, record: &LogRecord) -> Option<ActivityEvent> {
+        let mut event = self.pending_tool.take()?;
+
+        if let Activity::ToolCall { tool, duration_ms, .. } = &mut event.activity {
+            // 日志中明确给出的耗时优先
+            let explicit = record
+                .message
+                .contains(tool.as_str())
+                .then(|| patterns().duration.captures(&record.message))
+                .flatten()
+                .and_then(|caps| {
+                    let value: f64 = caps[1].parse().ok()?;
+                    Some(if caps[2].eq_ignore_ascii_case("s") { value * 1000.0 } else { value } as u64)
+                });
+
+            *duration_ms = explicit.or_else(|| {
+                let start = parse_timestamp(event.timestamp.as_deref())?;
+                let end = parse_timestamp(record.timestamp.as_deref())?;
+                u64::try_from((end - start).num_milliseconds()).ok()
+            });
+        }
+
+        Some(event)
+    }
+
+    /// 识别单条记录
+    fn classify(&self, record: &LogRecord) -> Option<Activity> {
+        let p = patterns();
+        let message = record.message.as_str();
+
+        if let Some(caps) = p.inbound.captures(message) {
+            return Some(Activity::InboundMessage {
+                channel: caps[1].to_string(),
+                sender: caps[2].to_string(),
+                preview: preview(&caps[3]),
+            });
+        }
+
+        if let Some(caps) = p.outbound.captures(message) {
+            return Some(Activity::OutboundReply {
+                channel: caps[1].to_string(),
+                recipient: caps[2].to_string(),
+                preview: preview(&caps[3]),
+            });
+        }
+
+        if let Some(caps) = p.tool_call.captures(message) {
+            let arguments = caps.get(2).map(|m| m.as_str()).unwrap_or("");
+            let arguments = arguments.strip_suffix(')').unwrap_or(arguments);
+            return Some(Activity::ToolCall {
+                tool: caps[1].to_string(),
+                arguments: preview(arguments),
+                duration_ms: None,
+            });
+        }
+
+        let from_provider = record.module.as_deref().map(|m| m.contains("providers")).unwrap_or(false);
+        let text = std::iter::once(message)
+            .chain(record.exception_line())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if record.level >= LogLevel::Error && (from_provider || p.llm.is_match(&text)) {
+            let provider = record
+                .module
+                .as_deref()
+                .and_then(|m| p.provider_module.captures(m))
+                .map(|caps| caps[1].to_string());
+            let model = p.model.captures(&text).map(|caps| caps[1].to_string());
+
+            return Some(Activity::LlmError {
+                provider,
+                model,
+                message: preview(record.exception_line().unwrap_or(message)),
+            });
+        }
+
+        None
+    }
+
+    /// 输入一条记录，返回识别出的活动（工具调用在下一条 agent 日志到来时才输出）
+    pub fn push(&mut self, record: &LogRecord) -> Vec<ActivityEvent> {
+        let mut events = Vec::new();
+        let activity = self.classify(record);
+
+        if self.pending_tool.is_some() && (activity.is_some() || is_agent_record(record)) {
+            events.extend(self.finish_tool(record));
+        }
+
+        match activity {
+            Some(activity @ Activity::ToolCall { .. }) => {
+                self.pending_tool = Some(self.event(record, activity));
+            }
+            Some(activity) => events.push(self.event(record, activity)),
+            None => {}
+        }
+
+        events
+    }
+}
+
+struct FeedState {
+    parser: ActivityParser,
+    history: VecDeque<ActivityEvent>,
+    subscription: Option<u64>,
+}
+
+/// 活动动态
+pub struct ActivityFeed {
+    state: Arc<Mutex<FeedState>>,
+}
+
+impl ActivityFeed {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FeedState {
+                parser: ActivityParser::new(),
+                history: VecDeque::new(),
+                subscription: None,
+            })),
+        }
+    }
+
+    /// 处理一批记录，返回新的活动
+    pub fn process(&self, records: &[LogRecord]) -> Vec<ActivityEvent> {
+        let mut state = self.state.lock().unwrap();
+        let events: Vec<ActivityEvent> = records.iter().flat_map(|r| state.parser.push(r)).collect();
+
+        for event in &events {
+            if state.history.len() >= MAX_HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(event.clone());
+        }
+
+        events
+    }
+
+    /// 最近的活动（按时间正序），可按类型过滤
+    pub fn history(&self, limit: usize, kinds: Option<&[String]>) -> Vec<ActivityEvent> {
+        let state = self.state.lock().unwrap();
+        let mut events: Vec<ActivityEvent> = state
+            .history
+            .iter()
+            .rev()
+            .filter(|e| kinds.map(|k| k.iter().any(|k| k == e.activity.kind())).unwrap_or(true))
+            .take(limit)
+            .cloned()
+            .collect();
+        events.reverse();
+        events
+    }
+
+    /// 用日志末尾的内容回填历史，再订阅日志流；notify 负责推送新活动
+    pub fn start<F>(&self, hub: &LogStreamHub, notify: F) -> Result<(), String>
+    where
+        F: Fn(&[ActivityEvent]) + Send + 'static,
+    {
+        if self.state.lock().unwrap().subscription.is_some() {
+            return Ok(());
+        }
+
+        let lines = logger::read_recent_lines(BACKFILL_BYTES).unwrap_or_default();
+        // 活动会推送给所有窗口，始终脱敏
+        let redactor = logredact::current();
+        let mut records = logparse::parse_lines(&lines);
+        records.iter_mut().for_each(|r| redactor.redact_record(r));
+        self.process(&records);
+
+        let feed = ActivityFeed { state: self.state.clone() };
+        let options = StreamOptions {
+            batch_ms: Some(ACTIVITY_BATCH_MS),
+            ..Default::default()
+        };
+
+        let id = hub.subscribe("activity", options, false, move |batch| {
+            let events = feed.process(&batch.records);
+            if !events.is_empty() {
+                notify(&events);
+            }
+            Ok(())
+        })?;
+
+        self.state.lock().unwrap().subscription = Some(id);
+        Ok(())
+    }
+}
+
+/// 应用启动时开始解析活动，新活动以 activity 事件推送
+pub fn start(app: &tauri::AppHandle, feed: &ActivityFeed, hub: &LogStreamHub) -> Result<(), String> {
+    let app = app.clone();
+    feed.start(hub, move |events| {
+        let _ = app.emit("activity", events);
+    })
+}
+
+/// 获取最近的活动
+#[tauri::command]
+pub async fn get_activity(
+    limit: Option<usize>,
+    kinds: Option<Vec<String>>,
+    feed: State<'_, ActivityFeed>,
+) -> Result<Vec<ActivityEvent>, String> {
+    let limit = limit.unwrap_or(100).clamp(1, MAX_HISTORY);
+    Ok(feed.history(limit, kinds.as_deref()))
+}
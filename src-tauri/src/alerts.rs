@@ -0,0 +1,470 @@
+// 日志告警模块
+// 告警规则作为日志流的一个订阅者，对实时日志逐条求值：级别阈值、正则匹配、或一段时间内的出现次数。
+// 每条规则有冷却时间，触发时发送系统通知，并在托盘图标上显示未读告警数。
+// 规则保存在 ~/.nanobot/alerts.json。
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::fsutil;
+use crate::logparse::{LogLevel, LogRecord};
+use crate::logstream::{LogStreamHub, StreamOptions};
+use crate::menu;
+
+/// 内存中保留的告警历史条数
+const MAX_HISTORY: usize = 200;
+
+/// 告警订阅的合并间隔（毫秒）
+const ALERT_BATCH_MS: u64 = 500;
+
+/// 告警条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AlertCondition {
+    /// 出现不低于指定级别的日志
+    #[serde(rename_all = "camelCase")]
+    Level { min_level: LogLevel },
+    /// 日志内容（首行和堆栈）匹配正则，可选级别下限
+    #[serde(rename_all = "camelCase")]
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        min_level: Option<LogLevel>,
+    },
+    /// window_minutes 分钟内出现 count 条不低于指定级别的日志
+    #[serde(rename_all = "camelCase")]
+    Rate {
+        #[serde(default = "default_rate_level")]
+        min_level: LogLevel,
+        count: usize,
+        window_minutes: u64,
+    },
+}
+
+fn default_rate_level() -> LogLevel {
+    LogLevel::Error
+}
+
+/// 告警规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    /// 为空时自动生成
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub condition: AlertCondition,
+    /// 冷却时间，触发后在此期间内不再重复通知
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cooldown() -> u64 {
+    300
+}
+
+impl AlertRule {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("告警规则名称不能为空".to_string());
+        }
+
+        match &self.condition {
+            AlertCondition::Level { .. } => {}
+            AlertCondition::Regex { pattern, .. } => {
+                if pattern.is_empty() {
+                    return Err("正则表达式不能为空".to_string());
+                }
+                Regex::new(pattern).map_err(|e| format!("无效的正则表达式: {}", e))?;
+            }
+            AlertCondition::Rate { count, window_minutes, .. } => {
+                if *count == 0 {
+                    return Err("触发次数必须大于 0".to_string());
+                }
+                if *window_minutes == 0 || *window_minutes > 24 * 60 {
+                    return Err("统计时间窗口必须在 1 到 1440 分钟之间".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 一次告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEvent {
+    pub rule_id: String,
+    pub rule_name: String,
+    /// 通知正文
+    pub message: String,
+    /// 触发告警的日志
+    pub record: LogRecord,
+    /// 触发时间（RFC 3339）
+    pub triggered_at: String,
+}
+
+/// 规则文件格式
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AlertsFile {
+    #[serde(default)]
+    rules: Vec<AlertRule>,
+}
+
+/// 获取规则文件路径
+fn get_alerts_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".nanobot").join("alerts.json")
+}
+
+/// 加载规则，文件不存在或无法解析时为空
+fn load_rules() -> Vec<AlertRule> {
+    std::fs::read_to_string(get_alerts_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<AlertsFile>(&content).ok())
+        .map(|file| file.rules)
+        .unwrap_or_default()
+}
+
+/// 保存规则
+fn save_rules(rules: &[AlertRule]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&AlertsFile { rules: rules.to_vec() })
+        .map_err(|e| format!("序列化告警规则失败: {}", e))?;
+
+    fsutil::atomic_write(&get_alerts_path(), content)
+        .map_err(|e| format!("保存告警规则失败: {}", e))
+}
+
+/// 规则运行时状态
+struct RuleState {
+    rule: AlertRule,
+    regex: Option<Regex>,
+    /// 上次触发时间
+    last_fired: Option<Instant>,
+    /// 频率规则在时间窗口内命中的时间
+    hits: VecDeque<Instant>,
+}
+
+impl RuleState {
+    fn new(rule: AlertRule) -> Self {
+        let regex = match &rule.condition {
+            AlertCondition::Regex { pattern, .. } => Regex::new(pattern).ok(),
+            _ => None,
+        };
+
+        Self {
+            rule,
+            regex,
+            last_fired: None,
+            hits: VecDeque::new(),
+        }
+    }
+
+    /// 判断记录是否满足条件，满足时返回通知正文
+    fn check(&mut self, record: &LogRecord, now: Instant) -> Option<String> {
+        match &self.rule.condition {
+            AlertCondition::Level { min_level } => {
+                (record.level >= *min_level && record.level != LogLevel::Unknown)
+                    .then(|| format!("[{}] {}", record.level.as_str(), record.message))
+            }
+            AlertCondition::Regex { min_level, .. } => {
+                if min_level.map(|l| record.level < l).unwrap_or(false) {
+                    return None;
+                }
+                let regex = self.regex.as_ref()?;
+                record
+                    .raw_lines()
+                    .any(|line| regex.is_match(line))
+                    .then(|| format!("[{}] {}", record.level.as_str(), record.message))
+            }
+            AlertCondition::Rate { min_level, count, window_minutes } => {
+                if record.level < *min_level || record.level == LogLevel::Unknown {
+                    return None;
+                }
+
+                let window = Duration::from_secs(window_minutes * 60);
+                self.hits.push_back(now);
+                while self.hits.front().map(|t| now.duration_since(*t) > window).unwrap_or(false) {
+                    self.hits.pop_front();
+                }
+
+                (self.hits.len() >= *count).then(|| {
+                    format!(
+                        "{} 分钟内出现 {} 条 {} 及以上级别的日志，最近一条：{}",
+                        window_minutes,
+                        self.hits.len(),
+                        min_level.as_str(),
+                        record.message
+                    )
+                })
+            }
+        }
+    }
+
+    fn in_cooldown(&self, now: Instant) -> bool {
+        self.last_fired
+            .map(|t| now.duration_since(t) < Duration::from_secs(self.rule.cooldown_secs))
+            .unwrap_or(false)
+    }
+}
+
+struct EngineState {
+    rules: Vec<RuleState>,
+    history: VecDeque<AlertEvent>,
+    unread: usize,
+    /// 告警使用的日志流订阅
+    subscription: Option<u64>,
+}
+
+/// 告警引擎
+pub struct AlertEngine {
+    state: Arc<Mutex<EngineState>>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(EngineState {
+                rules: load_rules().into_iter().map(RuleState::new).collect(),
+                history: VecDeque::new(),
+                unread: 0,
+                subscription: None,
+            })),
+        }
+    }
+
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.state.lock().unwrap().rules.iter().map(|r| r.rule.clone()).collect()
+    }
+
+    /// 替换规则：已有规则保留冷却和计数状态
+    fn set_rules(&self, rules: Vec<AlertRule>) {
+        let mut state = self.state.lock().unwrap();
+        let mut old: HashMap<String, RuleState> = state.rules.drain(..).map(|r| (r.rule.id.clone(), r)).collect();
+
+        state.rules = rules
+            .into_iter()
+            .map(|rule| match old.remove(&rule.id) {
+                Some(mut existing) if existing.rule.condition == rule.condition => {
+                    existing.rule = rule;
+                    existing
+                }
+                Some(existing) => {
+                    let mut fresh = RuleState::new(rule);
+                    fresh.last_fired = existing.last_fired;
+                    fresh
+                }
+                None => RuleState::new(rule),
+            })
+            .collect();
+    }
+
+    /// 对一批日志求值，返回新触发的告警
+    pub fn process(&self, records: &[LogRecord]) -> Vec<AlertEvent> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let mut events = Vec::new();
+
+        for record in records {
+            for rule_state in state.rules.iter_mut().filter(|r| r.rule.enabled) {
+                let Some(message) = rule_state.check(record, now) else {
+                    continue;
+                };
+
+                if rule_state.in_cooldown(now) {
+                    continue;
+                }
+
+                rule_state.last_fired = Some(now);
+                rule_state.hits.clear();
+
+                events.push(AlertEvent {
+                    rule_id: rule_state.rule.id.clone(),
+                    rule_name: rule_state.rule.name.clone(),
+                    message,
+                    record: record.clone(),
+                    triggered_at: chrono::Local::now().to_rfc3339(),
+                });
+            }
+        }
+
+        for event in &events {
+            if state.history.len() >= MAX_HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(event.clone());
+        }
+        state.unread += events.len();
+
+        events
+    }
+
+    /// 告警历史（从新到旧）和未读数
+    pub fn history(&self) -> (Vec<AlertEvent>, usize) {
+        let state = self.state.lock().unwrap();
+        (state.history.iter().rev().cloned().collect(), state.unread)
+    }
+
+    /// 标记全部已读
+    pub fn acknowledge(&self) {
+        self.state.lock().unwrap().unread = 0;
+    }
+
+    /// 有启用的规则时订阅日志流，否则取消订阅
+    pub fn sync_subscription<F>(&self, hub: &LogStreamHub, notify: F) -> Result<(), String>
+    where
+        F: Fn(&[AlertEvent], usize) + Send + 'static,
+    {
+        let (active, subscription) = {
+            let state = self.state.lock().unwrap();
+            (state.rules.iter().any(|r| r.rule.enabled), state.subscription)
+        };
+
+        match (active, subscription) {
+            (true, None) => {
+                let engine = AlertEngine { state: self.state.clone() };
+                let options = StreamOptions {
+                    batch_ms: Some(ALERT_BATCH_MS),
+                    ..Default::default()
+                };
+
+                let id = hub.subscribe("alerts", options, false, move |batch| {
+                    let events = engine.process(&batch.records);
+                    if !events.is_empty() {
+                        let unread = engine.state.lock().unwrap().unread;
+                        notify(&events, unread);
+                    }
+                    Ok(())
+                })?;
+
+                self.state.lock().unwrap().subscription = Some(id);
+            }
+            (false, Some(id)) => {
+                hub.unsubscribe(id);
+                self.state.lock().unwrap().subscription = None;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// 发送系统通知、更新托盘未读数，并推送 log-alert 事件
+fn notify_alerts(app: &tauri::AppHandle, events: &[AlertEvent], unread: usize) {
+    for event in events {
+        if let Err(e) = app.notification().builder().title(&event.rule_name).body(&event.message).show() {
+            log::warn!("发送告警通知失败: {}", e);
+        }
+    }
+
+    menu::set_tray_badge(app, unread);
+    let _ = app.emit("log-alert", events);
+}
+
+/// 根据当前规则启动或停止告警（应用启动和规则变化时调用）
+pub fn sync(app: &tauri::AppHandle, engine: &AlertEngine, hub: &LogStreamHub) -> Result<(), String> {
+    let app = app.clone();
+    engine.sync_subscription(hub, move |events, unread| notify_alerts(&app, events, unread))
+}
+
+/// 生成规则 ID
+fn generate_rule_id(rules: &[AlertRule]) -> String {
+    let base = format!("rule-{}", chrono::Utc::now().timestamp_millis());
+    let mut id = base.clone();
+    let mut n = 1;
+    while rules.iter().any(|r| r.id == id) {
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+    id
+}
+
+/// 获取告警规则
+#[tauri::command]
+pub async fn list_alert_rules(engine: State<'_, AlertEngine>) -> Result<Vec<AlertRule>, String> {
+    Ok(engine.rules())
+}
+
+/// 添加或更新告警规则（id 为空或不存在时新增）
+#[tauri::command]
+pub async fn save_alert_rule(
+    rule: AlertRule,
+    app: tauri::AppHandle,
+    engine: State<'_, AlertEngine>,
+    hub: State<'_, LogStreamHub>,
+) -> Result<AlertRule, String> {
+    rule.validate()?;
+
+    let mut rules = engine.rules();
+    let mut rule = rule;
+
+    match rules.iter_mut().find(|r| !rule.id.is_empty() && r.id == rule.id) {
+        Some(existing) => *existing = rule.clone(),
+        None => {
+            if rule.id.is_empty() {
+                rule.id = generate_rule_id(&rules);
+            }
+            rules.push(rule.clone());
+        }
+    }
+
+    save_rules(&rules)?;
+    engine.set_rules(rules);
+    sync(&app, &engine, &hub)?;
+
+    Ok(rule)
+}
+
+/// 删除告警规则
+#[tauri::command]
+pub async fn delete_alert_rule(
+    id: String,
+    app: tauri::AppHandle,
+    engine: State<'_, AlertEngine>,
+    hub: State<'_, LogStreamHub>,
+) -> Result<(), String> {
+    let mut rules = engine.rules();
+    let before = rules.len();
+    rules.retain(|r| r.id != id);
+
+    if rules.len() == before {
+        return Err(format!("告警规则 {} 不存在", id));
+    }
+
+    save_rules(&rules)?;
+    engine.set_rules(rules);
+    sync(&app, &engine, &hub)
+}
+
+/// 获取告警历史和未读数
+#[tauri::command]
+pub async fn get_alert_history(engine: State<'_, AlertEngine>) -> Result<serde_json::Value, String> {
+    let (alerts, unread) = engine.history();
+
+    Ok(serde_json::json!({
+        "alerts": alerts,
+        "unread": unread,
+    }))
+}
+
+/// 将告警标记为已读并清除托盘未读数
+#[tauri::command]
+pub async fn acknowledge_alerts(app: tauri::AppHandle, engine: State<'_, AlertEngine>) -> Result<(), String> {
+    engine.acknowledge();
+    menu::set_tray_badge(&app, 0);
+    Ok(())
+}
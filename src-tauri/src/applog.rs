@@ -0,0 +1,352 @@
+// 应用日志模块
+// nanoboard 自身的日志（log 宏）写入 ~/.nanobot/nanoboard/nanoboard.log，按大小轮转，同时输出到 stderr。
+// 从桌面入口启动时没有终端，只输出到 stderr 的错误会丢失；写入文件后可以在界面中查看或随问题一起反馈。
+// 每行使用与 loguru 相同的格式（位置中的函数名处写源文件名），可以直接复用日志解析。
+
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::fsutil;
+use crate::logger;
+use crate::logparse::{self, LogLevel, LogRecord};
+use crate::logredact;
+use crate::logrotate::{LogRotationSettings, RotatingLog, RotationPolicy};
+
+/// 单个日志文件的最大字节数
+const MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// 保留的归档数量
+const MAX_FILES: usize = 3;
+
+/// 内存中保留的最近日志行数，供界面实时跟踪
+const RECENT_LINES: usize = 1000;
+
+/// 读取日志时从文件末尾读取的字节数
+const READ_BYTES: u64 = 512 * 1024;
+
+/// 默认返回的记录数
+const DEFAULT_READ_LIMIT: usize = 500;
+
+/// 当前日志级别（LevelFilter 的序号）
+static LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+
+static LOGGER: OnceLock<AppLogger> = OnceLock::new();
+
+thread_local! {
+    /// 正在写日志文件，期间产生的日志（如轮转失败）不再写文件，避免重入死锁
+    static WRITING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 应用日志设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppLogSettings {
+    /// 日志级别：off、error、warn、info、debug、trace
+    pub level: String,
+}
+
+impl Default for AppLogSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+/// 一页应用日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLogPage {
+    pub path: String,
+    pub records: Vec<LogRecord>,
+    /// 最后一行的序号，作为下次跟踪的起点
+    pub cursor: u64,
+    /// 起点之后的部分内容已不在内存中，需要重新读取
+    pub truncated: bool,
+}
+
+struct Recent {
+    next_seq: u64,
+    lines: VecDeque<(u64, String)>,
+}
+
+struct AppLogger {
+    file: Mutex<Option<RotatingLog>>,
+    recent: Mutex<Recent>,
+}
+
+/// 解析日志级别，兼容 loguru 的 warning
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    let level = level.trim().to_lowercase();
+    let level = if level == "warning" { "warn" } else { level.as_str() };
+    LevelFilter::from_str(level).map_err(|_| format!("无效的日志级别: {}", level))
+}
+
+fn current_level() -> LevelFilter {
+    LevelFilter::iter()
+        .nth(LEVEL.load(Ordering::Relaxed))
+        .unwrap_or(LevelFilter::Info)
+}
+
+fn set_level(level: LevelFilter) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+    log::set_max_level(level);
+}
+
+/// loguru 风格的级别名
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "ERROR",
+        Level::Warn => "WARNING",
+        Level::Info => "INFO",
+        Level::Debug => "DEBUG",
+        Level::Trace => "TRACE",
+    }
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = current_level();
+
+        // 依赖库（tauri、reqwest 等）的日志最多记录到 WARN，除非级别为 TRACE
+        if metadata.target().starts_with(env!("CARGO_CRATE_NAME")) || level == LevelFilter::Trace {
+            metadata.level() <= level
+        } else {
+            metadata.level() <= level.min(LevelFilter::Warn)
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let module = record.module_path().unwrap_or(record.target()).replace("::", ".");
+        let file = record
+            .file()
+            .and_then(|f| f.rsplit(['/', '\\']).next())
+            .unwrap_or("-");
+        let line = format!(
+            "{} | {:<8} | {}:{}:{} - {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            level_name(record.level()),
+            module,
+            file,
+            record.line().unwrap_or(0),
+            record.args()
+        );
+
+        let _ = writeln!(std::io::stderr(), "{}", line);
+
+        {
+            let mut recent = self.recent.lock().unwrap();
+            let seq = recent.next_seq;
+            recent.next_seq += 1;
+            if recent.lines.len() >= RECENT_LINES {
+                recent.lines.pop_front();
+            }
+            recent.lines.push_back((seq, line.clone()));
+        }
+
+        if WRITING.with(|w| w.replace(true)) {
+            return;
+        }
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.write_line("", line.as_bytes());
+        }
+        WRITING.with(|w| w.set(false));
+    }
+
+    fn flush(&self) {}
+}
+
+/// 获取应用日志文件路径
+pub fn get_app_log_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".nanobot").join("nanoboard").join("nanoboard.log")
+}
+
+/// 获取设置文件路径
+fn get_settings_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".nanobot").join("app_log.json")
+}
+
+/// 加载设置，文件不存在或无法解析时使用默认值
+pub fn load_settings() -> AppLogSettings {
+    fs::read_to_string(get_settings_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存设置
+fn save_settings(settings: &AppLogSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("序列化应用日志设置失败: {}", e))?;
+
+    fsutil::atomic_write(&get_settings_path(), content)
+        .map_err(|e| format!("保存应用日志设置失败: {}", e))
+}
+
+/// 打开日志文件
+fn open_log_file() -> std::io::Result<RotatingLog> {
+    let path = get_app_log_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    RotatingLog::open(&path, LogRotationSettings {
+        enabled: true,
+        policy: RotationPolicy::Size,
+        max_bytes: MAX_BYTES,
+        max_files: MAX_FILES,
+        max_age_days: 0,
+        compress: false,
+        tag_streams: false,
+    })
+}
+
+/// 安装应用日志，替代 env_logger
+/// 设置了 RUST_LOG（单个级别，如 debug）时优先使用，否则使用保存的级别
+pub fn init() {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|l| parse_level(&l).ok())
+        .or_else(|| parse_level(&load_settings().level).ok())
+        .unwrap_or(LevelFilter::Info);
+
+    let (file, open_error) = match open_log_file() {
+        Ok(file) => (Some(file), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let logger = LOGGER.get_or_init(|| AppLogger {
+        file: Mutex::new(file),
+        recent: Mutex::new(Recent {
+            next_seq: 1,
+            lines: VecDeque::new(),
+        }),
+    });
+
+    if log::set_logger(logger).is_ok() {
+        set_level(level);
+    }
+
+    if let Some(e) = open_error {
+        log::error!("打开应用日志文件失败，仅输出到 stderr: {}", e);
+    }
+}
+
+/// 解析日志行，过滤级别并按窗口脱敏
+fn to_records(label: &str, lines: &[String], min_level: Option<LogLevel>) -> Vec<LogRecord> {
+    let mut records = logparse::parse_lines(lines);
+    if let Some(min_level) = min_level {
+        records.retain(|r| r.level >= min_level);
+    }
+    logredact::redact_for_display(label, &mut records);
+    records
+}
+
+/// 内存中最新一行的序号
+fn latest_seq() -> u64 {
+    LOGGER
+        .get()
+        .map(|l| l.recent.lock().unwrap().next_seq - 1)
+        .unwrap_or(0)
+}
+
+/// 获取当前日志级别
+#[tauri::command]
+pub async fn get_app_log_level() -> Result<String, String> {
+    Ok(current_level().to_string().to_lowercase())
+}
+
+/// 调整日志级别，立即生效并保存
+#[tauri::command]
+pub async fn set_app_log_level(level: String) -> Result<String, String> {
+    let filter = parse_level(&level)?;
+    let level = filter.to_string().to_lowercase();
+
+    save_settings(&AppLogSettings { level: level.clone() })?;
+    set_level(filter);
+    log::info!("应用日志级别已调整为 {}", level);
+
+    Ok(level)
+}
+
+/// 读取最近的应用日志（当前文件不足时包括上一个归档）
+#[tauri::command]
+pub async fn read_app_log(
+    window: tauri::Window,
+    limit: Option<usize>,
+    min_level: Option<LogLevel>,
+) -> Result<AppLogPage, String> {
+    let limit = limit.unwrap_or(DEFAULT_READ_LIMIT).max(1);
+    let label = window.label().to_string();
+    // 先取序号，读取期间新写入的行会在下次跟踪时返回（可能重复一两行，不会遗漏）
+    let cursor = latest_seq();
+
+    tokio::task::spawn_blocking(move || {
+        let path = get_app_log_path();
+        let mut records = to_records(&label, &logger::read_tail_lines(&path, READ_BYTES)?, min_level);
+
+        if records.len() < limit {
+            let archive = path.with_file_name(format!("{}.1", path.file_name().unwrap_or_default().to_string_lossy()));
+            let mut older = to_records(&label, &logger::read_tail_lines(&archive, READ_BYTES)?, min_level);
+            older.append(&mut records);
+            records = older;
+        }
+
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+
+        Ok(AppLogPage {
+            path: path.to_string_lossy().to_string(),
+            records,
+            cursor,
+            truncated: false,
+        })
+    })
+    .await
+    .map_err(|e| format!("读取应用日志失败: {}", e))?
+}
+
+/// 跟踪应用日志：返回序号 cursor 之后新写入的记录
+#[tauri::command]
+pub async fn tail_app_log(window: tauri::Window, cursor: u64, min_level: Option<LogLevel>) -> Result<AppLogPage, String> {
+    let (lines, latest, truncated) = match LOGGER.get() {
+        Some(logger) => {
+            let recent = logger.recent.lock().unwrap();
+            let latest = recent.next_seq - 1;
+            // 序号比最新的还大（如 nanoboard 已重启），从头返回
+            let (cursor, restarted) = if cursor > latest { (0, true) } else { (cursor, false) };
+
+            let lines: Vec<String> = recent
+                .lines
+                .iter()
+                .filter(|(seq, _)| *seq > cursor)
+                .map(|(_, line)| line.clone())
+                .collect();
+            let dropped = recent.lines.front().map(|(seq, _)| *seq > cursor + 1).unwrap_or(false);
+            (lines, latest, restarted || dropped)
+        }
+        None => (Vec::new(), cursor, false),
+    };
+
+    Ok(AppLogPage {
+        path: get_app_log_path().to_string_lossy().to_string(),
+        records: to_records(window.label(), &lines, min_level),
+        cursor: latest,
+        truncated,
+    })
+}
@@ -0,0 +1,345 @@
+// 消息渠道模块
+// 汇总 config.json 中 channels 下各渠道的启用状态、凭据完整性和 allowFrom 列表，
+// 并从最近的 gateway 日志推断各渠道的运行状态
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+use crate::config;
+use crate::logger;
+use crate::logparse;
+use crate::logredact;
+
+/// 推断运行状态时读取的日志末尾字节数
+const RECENT_LOG_BYTES: u64 = 512 * 1024;
+
+/// 渠道定义
+pub struct ChannelSpec {
+    pub id: &'static str,
+    /// 启用前必须填写的凭据字段
+    pub required: &'static [&'static str],
+}
+
+/// 内置渠道（与前端 src/config/channels.ts 保持一致）
+pub static CHANNELS: &[ChannelSpec] = &[
+    ChannelSpec { id: "telegram", required: &["token"] },
+    ChannelSpec { id: "discord", required: &["token"] },
+    ChannelSpec { id: "whatsapp", required: &[] },
+    ChannelSpec { id: "mochat", required: &[] },
+    ChannelSpec { id: "feishu", required: &["appId", "appSecret"] },
+    ChannelSpec { id: "dingtalk", required: &["clientId", "clientSecret"] },
+    ChannelSpec { id: "slack", required: &["botToken", "appToken"] },
+    ChannelSpec { id: "qq", required: &["appId", "secret"] },
+    ChannelSpec { id: "matrix", required: &["homeserver", "accessToken", "userId"] },
+    ChannelSpec {
+        id: "email",
+        required: &["imapHost", "imapUsername", "imapPassword", "smtpHost", "smtpUsername", "smtpPassword"],
+    },
+];
+
+/// 凭据字段状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    pub field: String,
+    pub present: bool,
+}
+
+/// 渠道运行状态（从 gateway 日志推断）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRuntime {
+    /// connected / error / disconnected / unknown
+    pub state: String,
+    pub last_event_at: Option<String>,
+    pub last_error: Option<String>,
+    pub last_message_at: Option<String>,
+}
+
+/// 渠道信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStatus {
+    pub id: String,
+    /// 是否为内置渠道
+    pub known: bool,
+    /// config.json 中是否存在该渠道
+    pub configured: bool,
+    pub enabled: bool,
+    pub credentials: Vec<CredentialStatus>,
+    pub missing_credentials: Vec<String>,
+    pub allow_from: Vec<String>,
+    pub runtime: ChannelRuntime,
+}
+
+/// 查找内置渠道定义
+pub fn find_channel_spec(id: &str) -> Option<&'static ChannelSpec> {
+    CHANNELS.iter().find(|c| c.id == id)
+}
+
+/// 返回渠道缺少的必填凭据
+pub fn missing_credentials(id: &str, channel: &JsonValue) -> Vec<String> {
+    let Some(spec) = find_channel_spec(id) else {
+        return Vec::new();
+    };
+
+    spec.required
+        .iter()
+        .filter(|field| !has_value(channel.get(**field)))
+        .map(|field| field.to_string())
+        .collect()
+}
+
+fn has_value(value: Option<&JsonValue>) -> bool {
+    match value {
+        Some(JsonValue::String(s)) => !s.trim().is_empty(),
+        Some(JsonValue::Null) | None => false,
+        Some(_) => true,
+    }
+}
+
+/// 规范化 allowFrom（前端可能保存为逗号分隔的字符串）
+fn normalize_allow_from(value: Option<&JsonValue>) -> Vec<String> {
+    let items: Vec<String> = match value {
+        Some(JsonValue::Array(arr)) => arr
+            .iter()
+            .filter_map(|v| match v {
+                JsonValue::String(s) => Some(s.clone()),
+                JsonValue::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Some(JsonValue::String(s)) => s.split([',', '\n']).map(|s| s.to_string()).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut result: Vec<String> = Vec::new();
+    for item in items {
+        let item = item.trim().to_string();
+        if !item.is_empty() && !result.contains(&item) {
+            result.push(item);
+        }
+    }
+    result
+}
+
+/// 生成渠道的 JSON Pointer
+fn channel_pointer(id: &str) -> String {
+    format!("/channels/{}", id.replace('~', "~0").replace('/', "~1"))
+}
+
+/// 只允许修改内置渠道或 config.json 中已有的渠道，避免拼错的名称写入一个新渠道
+fn ensure_channel_exists(config: &JsonValue, id: &str) -> Result<(), String> {
+    let configured = config
+        .get("channels")
+        .and_then(|c| c.as_object())
+        .map(|c| c.contains_key(id))
+        .unwrap_or(false);
+
+    if find_channel_spec(id).is_some() || configured {
+        Ok(())
+    } else {
+        Err(format!("未知的渠道: {}", id))
+    }
+}
+
+/// 判断日志行是否与渠道相关
+/// 匹配模块路径（nanobot.channels.telegram）或独立出现的渠道名
+fn line_mentions_channel(line_lower: &str, id: &str) -> bool {
+    if line_lower.contains(&format!("channels.{}", id)) {
+        return true;
+    }
+
+    line_lower.match_indices(id).any(|(index, _)| {
+        let before = line_lower[..index].chars().next_back();
+        let after = line_lower[index + id.len()..].chars().next();
+        !before.map(|c| c.is_alphanumeric()).unwrap_or(false)
+            && !after.map(|c| c.is_alphanumeric()).unwrap_or(false)
+    })
+}
+
+/// 提取 loguru 日志行的时间戳（"2025-01-01 12:00:00.123 | INFO | ..."），管道模式下的流标记会先去掉
+fn line_timestamp(line: &str) -> Option<String> {
+    let (_, line) = logparse::split_stream_tag(line);
+    let (time, _) = line.split_once(" | ")?;
+    let time = time.trim();
+
+    if time.len() >= 19 && time.as_bytes()[4] == b'-' && time.as_bytes()[10] == b' ' {
+        Some(time.to_string())
+    } else {
+        None
+    }
+}
+
+/// 从日志行推断各渠道的运行状态
+/// 只统计最近一次 gateway 启动之后的日志
+pub fn runtime_from_lines(lines: &[String], ids: &[String]) -> Vec<ChannelRuntime> {
+    let start = lines
+        .iter()
+        .rposition(|l| l.contains("Starting nanobot gateway"))
+        .unwrap_or(0);
+
+    let mut runtimes: Vec<ChannelRuntime> = ids
+        .iter()
+        .map(|_| ChannelRuntime { state: "unknown".to_string(), ..Default::default() })
+        .collect();
+
+    for line in &lines[start..] {
+        let lower = line.to_lowercase();
+
+        for (id, runtime) in ids.iter().zip(runtimes.iter_mut()) {
+            let id = id.to_lowercase();
+
+            // agent 处理入站消息时记录 "message from <channel>:<sender>"
+            if lower.contains(&format!("message from {}:", id)) {
+                runtime.last_message_at = line_timestamp(line);
+                continue;
+            }
+
+            if !line_mentions_channel(&lower, &id) {
+                continue;
+            }
+
+            let is_error = ["error", "critical", "failed", "exception"]
+                .iter()
+                .any(|k| lower.contains(k));
+
+            let state = if lower.contains("disconnect") || lower.contains("stopped") || lower.contains("stopping") {
+                "disconnected"
+            } else if is_error {
+                "error"
+            } else if ["connected", "started", "starting", "running", "listening", "ready", "logged in"]
+                .iter()
+                .any(|k| lower.contains(k))
+            {
+                "connected"
+            } else {
+                continue;
+            };
+
+            runtime.state = state.to_string();
+            runtime.last_event_at = line_timestamp(line);
+
+            if state == "error" {
+                let message = line.rsplit_once(" - ").map(|(_, m)| m).unwrap_or(line);
+                runtime.last_error = Some(message.trim().to_string());
+            }
+        }
+    }
+
+    runtimes
+}
+
+/// 列出所有渠道
+/// 内置渠道即使未配置也会返回（configured 为 false），便于前端展示
+#[tauri::command]
+pub async fn list_channels(window: tauri::Window) -> Result<Vec<ChannelStatus>, String> {
+    let config = config::read_config_file().unwrap_or_else(|_| json!({}));
+    let configured = config.get("channels").and_then(|v| v.as_object()).cloned().unwrap_or_default();
+
+    let mut ids: Vec<String> = CHANNELS.iter().map(|c| c.id.to_string()).collect();
+    for (id, value) in &configured {
+        // channels 下也可能有 sendProgress 等非渠道字段
+        if value.is_object() && !ids.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+
+    let lines = logger::read_recent_lines(RECENT_LOG_BYTES).unwrap_or_default();
+    let mut runtimes = runtime_from_lines(&lines, &ids);
+
+    // 错误信息来自 gateway 日志，可能包含密钥
+    for runtime in &mut runtimes {
+        runtime.last_error = runtime.last_error.as_deref().map(|e| logredact::redact_text_for_display(window.label(), e));
+    }
+
+    let channels = ids
+        .into_iter()
+        .zip(runtimes)
+        .map(|(id, runtime)| {
+            let channel = configured.get(&id).cloned().unwrap_or_else(|| json!({}));
+            let spec = find_channel_spec(&id);
+
+            let credentials = spec
+                .map(|s| s.required)
+                .unwrap_or_default()
+                .iter()
+                .map(|field| CredentialStatus {
+                    field: field.to_string(),
+                    present: has_value(channel.get(*field)),
+                })
+                .collect();
+
+            ChannelStatus {
+                known: spec.is_some(),
+                configured: configured.contains_key(&id),
+                enabled: channel.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false),
+                credentials,
+                missing_credentials: missing_credentials(&id, &channel),
+                allow_from: normalize_allow_from(channel.get("allowFrom")),
+                runtime,
+                id,
+            }
+        })
+        .collect();
+
+    Ok(channels)
+}
+
+/// 启用或禁用渠道
+#[tauri::command]
+pub async fn set_channel_enabled(channel: String, enabled: bool) -> Result<JsonValue, String> {
+    if channel.trim().is_empty() {
+        return Err("渠道名称不能为空".to_string());
+    }
+
+    let pointer = format!("{}/enabled", channel_pointer(&channel));
+
+    let validation = config::update_config(|config| {
+        ensure_channel_exists(config, &channel)?;
+        config::set_value_at_pointer(config, &pointer, json!(enabled))
+    })?;
+
+    let current = config::read_config_file()?;
+    let missing = current
+        .pointer(&channel_pointer(&channel))
+        .map(|c| missing_credentials(&channel, c))
+        .unwrap_or_default();
+
+    let message = if !enabled {
+        format!("已禁用渠道 {}", channel)
+    } else if missing.is_empty() {
+        format!("已启用渠道 {}", channel)
+    } else {
+        format!("已启用渠道 {}，但缺少凭据：{}", channel, missing.join(", "))
+    };
+
+    Ok(json!({
+        "success": true,
+        "message": message,
+        "missingCredentials": missing,
+        "validation": validation
+    }))
+}
+
+/// 设置渠道的 allowFrom 列表（空列表表示不限制发送者）
+#[tauri::command]
+pub async fn set_channel_allow_from(channel: String, allow_from: Vec<String>) -> Result<JsonValue, String> {
+    if channel.trim().is_empty() {
+        return Err("渠道名称不能为空".to_string());
+    }
+
+    let allow_from = normalize_allow_from(Some(&json!(allow_from)));
+    let pointer = format!("{}/allowFrom", channel_pointer(&channel));
+
+    let validation = config::update_config(|config| {
+        ensure_channel_exists(config, &channel)?;
+        config::set_value_at_pointer(config, &pointer, json!(allow_from))
+    })?;
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已更新渠道 {} 的 allowFrom", channel),
+        "allowFrom": allow_from,
+        "validation": validation
+    }))
+}
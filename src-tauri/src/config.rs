@@ -3,10 +3,15 @@ use dirs::home_dir;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::State;
 use chrono::Utc;
 
+use crate::channel;
+use crate::fsutil;
+use crate::provider;
+use crate::secrets;
 use crate::AppState;
 
 /// 获取nanobot配置文件路径
@@ -46,16 +51,23 @@ fn create_history_backup() -> Result<()> {
     }
 
     // 读取当前配置
-    let content = fs::read_to_string(&config_path)
+    let mut content = fs::read_to_string(&config_path)
         .context("读取配置文件失败")?;
 
+    // 快照中的密钥使用本地口令加密；无法解析的配置按原样备份
+    if let Ok(config) = serde_json::from_str::<JsonValue>(&content) {
+        let encrypted = secrets::encrypt_secrets(&config)?;
+        content = serde_json::to_string_pretty(&encrypted)
+            .context("序列化备份失败")?;
+    }
+
     // 生成备份文件名（使用时间戳）
     let timestamp = Utc::now().timestamp();
     let backup_filename = format!("config_{}.json", timestamp);
     let backup_path = history_dir.join(&backup_filename);
 
     // 写入备份文件
-    fs::write(&backup_path, content)
+    fsutil::atomic_write(&backup_path, content)
         .context("写入备份文件失败")?;
 
     // 清理旧备份（保留最近10个）
@@ -119,7 +131,8 @@ pub fn load_config_internal() -> Result<JsonValue, String> {
 /// 加载配置文件
 #[tauri::command]
 pub async fn load_config(state: State<'_, AppState>) -> Result<JsonValue, String> {
-    let config = load_config_internal()?;
+    // 返回前端前对密钥打码，明文需通过 reveal_config_secret 单独获取
+    let config = secrets::mask_secrets(&load_config_internal()?);
 
     // 保存配置路径到状态
     let config_path = get_config_path_internal().map_err(|e| e.to_string())?;
@@ -130,9 +143,17 @@ pub async fn load_config(state: State<'_, AppState>) -> Result<JsonValue, String
 
 /// 保存配置文件
 #[tauri::command]
-pub async fn save_config(config: JsonValue) -> Result<(), String> {
+pub async fn save_config(mut config: JsonValue) -> Result<(), String> {
     let config_path = get_config_path_internal().map_err(|e| e.to_string())?;
 
+    // 与局部更新共用写入锁，读取当前配置到写入完成之间不会被其他修改插入
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // 前端回传的是打码后的配置，未修改的密钥需要还原为原值
+    if let Ok(current) = read_config_file() {
+        secrets::unmask_secrets(&mut config, &current);
+    }
+
     // 在保存前创建历史备份
     // 如果备份失败，阻止保存以保护用户配置
     if config_path.exists() {
@@ -150,7 +171,7 @@ pub async fn save_config(config: JsonValue) -> Result<(), String> {
     let content = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("序列化配置失败: {}", e))?;
 
-    fs::write(&config_path, content)
+    fsutil::atomic_write(&config_path, content)
         .map_err(|e| format!("写入配置文件失败: {}", e))?;
 
     Ok(())
@@ -166,6 +187,11 @@ pub async fn get_config_path() -> Result<String, String> {
 /// 验证配置是否有效
 #[tauri::command]
 pub async fn validate_config(config: JsonValue) -> Result<JsonValue, String> {
+    Ok(validate_config_internal(&config))
+}
+
+/// 验证配置（内部函数），返回 { valid, errors, warnings }
+pub fn validate_config_internal(config: &JsonValue) -> JsonValue {
     let mut errors = Vec::new();
     let mut warnings = Vec::new();
 
@@ -186,6 +212,11 @@ pub async fn validate_config(config: JsonValue) -> Result<JsonValue, String> {
             if let Some(model) = defaults.get("model") {
                 if model.as_str().map(|s| s.is_empty()).unwrap_or(true) {
                     errors.push("默认model不能为空".to_string());
+                } else if let Some(name) = model.as_str() {
+                    // 只和本地缓存中该 provider 拉取到的模型列表比较，不发起网络请求
+                    if let Some(warning) = provider::unknown_model_warning(name) {
+                        warnings.push(warning);
+                    }
                 }
             }
         }
@@ -201,14 +232,29 @@ pub async fn validate_config(config: JsonValue) -> Result<JsonValue, String> {
             if enabled_count == 0 {
                 warnings.push("没有启用任何消息渠道".to_string());
             }
+
+            for (id, value) in obj {
+                if !value.get("enabled").and_then(|e| e.as_bool()).unwrap_or(false) {
+                    continue;
+                }
+                let missing = channel::missing_credentials(id, value);
+                if !missing.is_empty() {
+                    warnings.push(format!("渠道 {} 已启用但缺少凭据：{}", id, missing.join(", ")));
+                }
+            }
         }
     }
 
-    Ok(serde_json::json!({
+    // 检查旧版本 nanobot 遗留的字段
+    for migration in pending_migrations(config) {
+        warnings.push(format!("检测到已废弃的配置字段：{}。可执行配置迁移自动更新", migration.description));
+    }
+
+    serde_json::json!({
         "valid": errors.is_empty(),
         "errors": errors,
         "warnings": warnings
-    }))
+    })
 }
 
 /// 获取配置历史版本列表
@@ -274,9 +320,17 @@ pub async fn restore_config_version(filename: String) -> Result<(), String> {
     }
 
     // 读取备份文件
-    let content = fs::read_to_string(&backup_path)
+    let mut content = fs::read_to_string(&backup_path)
         .map_err(|e| format!("读取备份文件失败: {}", e))?;
 
+    // 解密快照中的密钥
+    if let Ok(snapshot) = serde_json::from_str::<JsonValue>(&content) {
+        let decrypted = secrets::decrypt_secrets(&snapshot)
+            .map_err(|e| format!("解密备份文件失败: {:#}", e))?;
+        content = serde_json::to_string_pretty(&decrypted)
+            .map_err(|e| format!("序列化配置失败: {}", e))?;
+    }
+
     // 先备份当前配置
     if config_path.exists() {
         create_history_backup()
@@ -284,7 +338,7 @@ pub async fn restore_config_version(filename: String) -> Result<(), String> {
     }
 
     // 写入配置文件
-    fs::write(&config_path, content)
+    fsutil::atomic_write(&config_path, content)
         .map_err(|e| format!("恢复配置文件失败: {}", e))?;
 
     Ok(())
@@ -305,3 +359,638 @@ pub async fn delete_config_version(filename: String) -> Result<(), String> {
 
     Ok(())
 }
+
+/// 配置写入锁，保证局部更新的“读取-修改-写入”不会相互覆盖
+static CONFIG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 读取配置文件（配置不存在时返回错误，而不是 load_config 的提示对象）
+pub fn read_config_file() -> Result<JsonValue, String> {
+    let config_path = get_config_path_internal().map_err(|e| e.to_string())?;
+
+    if !config_path.exists() {
+        return Err("配置文件不存在，请先运行 nanobot onboard 初始化".to_string());
+    }
+
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("读取配置文件失败: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("解析配置文件失败: {}", e))
+}
+
+/// 校验并写入配置：校验失败时拒绝写入，写入前创建历史备份
+/// 返回 validate_config 的结果，便于前端展示警告
+pub fn write_validated_config(config: &JsonValue) -> Result<JsonValue, String> {
+    let validation = validate_config_internal(config);
+
+    if !validation["valid"].as_bool().unwrap_or(false) {
+        let errors: Vec<&str> = validation["errors"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(|e| e.as_str()).collect())
+            .unwrap_or_default();
+        return Err(format!("配置校验失败，未保存: {}", errors.join("; ")));
+    }
+
+    let config_path = get_config_path_internal().map_err(|e| e.to_string())?;
+
+    if config_path.exists() {
+        create_history_backup()
+            .map_err(|e| format!("创建配置备份失败，保存已取消: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("序列化配置失败: {}", e))?;
+
+    fsutil::atomic_write(&config_path, content)
+        .map_err(|e| format!("写入配置文件失败: {}", e))?;
+
+    Ok(validation)
+}
+
+/// 在写入锁内读取当前配置、应用修改并校验写入
+pub fn update_config<F>(mutate: F) -> Result<JsonValue, String>
+where
+    F: FnOnce(&mut JsonValue) -> Result<(), String>,
+{
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let current = read_config_file()?;
+    let mut config = current.clone();
+    mutate(&mut config)?;
+    secrets::unmask_secrets(&mut config, &current);
+    write_validated_config(&config)
+}
+
+/// 在写入锁内用新配置整体替换当前配置（同样会校验并备份）
+pub fn replace_config(config: &JsonValue) -> Result<JsonValue, String> {
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_validated_config(config)
+}
+
+/// 比较两份配置，返回差异列表
+/// 每项为 { path, kind: added/removed/changed, old, new }，path 为 JSON Pointer
+pub fn diff_config_values(old: &JsonValue, new: &JsonValue) -> Vec<JsonValue> {
+    let mut changes = Vec::new();
+    diff_values_at(String::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_values_at(path: String, old: &JsonValue, new: &JsonValue, changes: &mut Vec<JsonValue>) {
+    match (old, new) {
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            for (key, old_value) in a {
+                let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match b.get(key) {
+                    Some(new_value) => diff_values_at(child, old_value, new_value, changes),
+                    None => changes.push(serde_json::json!({
+                        "path": child, "kind": "removed", "old": old_value, "new": null
+                    })),
+                }
+            }
+            for (key, new_value) in b {
+                if !a.contains_key(key) {
+                    let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                    changes.push(serde_json::json!({
+                        "path": child, "kind": "added", "old": null, "new": new_value
+                    }));
+                }
+            }
+        }
+        _ if old != new => changes.push(serde_json::json!({
+            "path": path, "kind": "changed", "old": old, "new": new
+        })),
+        _ => {}
+    }
+}
+
+/// 解析 JSON Pointer（RFC 6901）为路径片段
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if !pointer.starts_with('/') {
+        return Err(format!("无效的 JSON Pointer: {}（必须以 / 开头）", pointer));
+    }
+
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// 在指定路径写入值，缺失的中间对象会被自动创建
+pub fn set_value_at_pointer(root: &mut JsonValue, pointer: &str, value: JsonValue) -> Result<(), String> {
+    let tokens = parse_pointer(pointer)?;
+
+    let Some((last, parents)) = tokens.split_last() else {
+        if !value.is_object() {
+            return Err("配置根节点必须是对象".to_string());
+        }
+        *root = value;
+        return Ok(());
+    };
+
+    let mut current = root;
+    for token in parents {
+        current = match current {
+            JsonValue::Object(map) => map
+                .entry(token.clone())
+                .or_insert_with(|| JsonValue::Object(serde_json::Map::new())),
+            JsonValue::Array(arr) => {
+                let index = parse_array_index(token, arr.len())?;
+                &mut arr[index]
+            }
+            _ => return Err(format!("路径 {} 经过了非容器节点 {}", pointer, token)),
+        };
+    }
+
+    match current {
+        JsonValue::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        JsonValue::Array(arr) => {
+            if last == "-" {
+                arr.push(value);
+            } else {
+                let index = parse_array_index(last, arr.len() + 1)?;
+                if index == arr.len() {
+                    arr.push(value);
+                } else {
+                    arr[index] = value;
+                }
+            }
+        }
+        _ => return Err(format!("路径 {} 的父节点不是对象或数组", pointer)),
+    }
+
+    Ok(())
+}
+
+/// 删除指定路径的值，返回被删除的值
+pub fn remove_value_at_pointer(root: &mut JsonValue, pointer: &str) -> Result<JsonValue, String> {
+    let tokens = parse_pointer(pointer)?;
+
+    let Some((last, parents)) = tokens.split_last() else {
+        return Err("不能删除配置根节点".to_string());
+    };
+
+    let parent_pointer: String = parents
+        .iter()
+        .map(|t| format!("/{}", t.replace('~', "~0").replace('/', "~1")))
+        .collect();
+
+    let not_found = || format!("路径 {} 不存在", pointer);
+
+    match root.pointer_mut(&parent_pointer).ok_or_else(not_found)? {
+        JsonValue::Object(map) => map.remove(last).ok_or_else(not_found),
+        JsonValue::Array(arr) => {
+            let index = parse_array_index(last, arr.len())?;
+            Ok(arr.remove(index))
+        }
+        _ => Err(not_found()),
+    }
+}
+
+fn parse_array_index(token: &str, len: usize) -> Result<usize, String> {
+    let index: usize = token
+        .parse()
+        .map_err(|_| format!("无效的数组下标: {}", token))?;
+
+    if index >= len {
+        return Err(format!("数组下标越界: {}", index));
+    }
+
+    Ok(index)
+}
+
+/// 获取指定 JSON Pointer 处的配置值，不存在时返回 null
+#[tauri::command]
+pub async fn get_config_value(pointer: String) -> Result<Option<JsonValue>, String> {
+    let config = read_config_file()?;
+    parse_pointer(&pointer)?;
+    Ok(config.pointer(&pointer).map(|value| match value.as_str() {
+        Some(s) if secrets::is_secret_pointer(&pointer) => JsonValue::String(secrets::mask_secret(s)),
+        _ => secrets::mask_secrets(value),
+    }))
+}
+
+/// 设置指定 JSON Pointer 处的配置值
+#[tauri::command]
+pub async fn set_config_value(pointer: String, value: JsonValue) -> Result<JsonValue, String> {
+    update_config(|config| set_value_at_pointer(config, &pointer, value))
+}
+
+/// 删除指定 JSON Pointer 处的配置值
+#[tauri::command]
+pub async fn delete_config_value(pointer: String) -> Result<JsonValue, String> {
+    update_config(|config| remove_value_at_pointer(config, &pointer).map(|_| ()))
+}
+
+/// 应用 RFC 6902 JSON Patch，任一操作失败时整体不生效
+#[tauri::command]
+pub async fn patch_config(patch: JsonValue) -> Result<JsonValue, String> {
+    let patch: json_patch::Patch = serde_json::from_value(patch)
+        .map_err(|e| format!("解析 JSON Patch 失败: {}", e))?;
+
+    update_config(|config| {
+        json_patch::patch(config, &patch)
+            .map_err(|e| format!("应用 JSON Patch 失败: {}", e))
+    })
+}
+
+/// 配置导入导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// 解析格式：优先使用显式指定的格式，否则根据文件扩展名判断
+    fn detect(format: Option<&str>, path: &Path) -> Result<Self, String> {
+        let name = match format {
+            Some(f) if !f.trim().is_empty() => f.trim().to_lowercase(),
+            _ => path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_else(|| "json".to_string()),
+        };
+
+        match name.as_str() {
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            other => Err(format!("不支持的配置格式: {}（可选 json、yaml、toml）", other)),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+        }
+    }
+
+    fn serialize(self, config: &JsonValue) -> Result<String, String> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(config)
+                .map_err(|e| format!("序列化 JSON 失败: {}", e)),
+            Self::Yaml => serde_yaml::to_string(config)
+                .map_err(|e| format!("序列化 YAML 失败: {}", e)),
+            // TOML 没有 null，导出时省略值为 null 的字段
+            Self::Toml => toml::to_string_pretty(&strip_nulls(config))
+                .map_err(|e| format!("序列化 TOML 失败: {}", e)),
+        }
+    }
+
+    fn deserialize(self, content: &str) -> Result<JsonValue, String> {
+        let config: JsonValue = match self {
+            Self::Json => serde_json::from_str(content)
+                .map_err(|e| format!("解析 JSON 失败: {}", e))?,
+            Self::Yaml => serde_yaml::from_str(content)
+                .map_err(|e| format!("解析 YAML 失败: {}", e))?,
+            Self::Toml => toml::from_str(content)
+                .map_err(|e| format!("解析 TOML 失败: {}", e))?,
+        };
+
+        if !config.is_object() {
+            return Err("配置文件顶层必须是对象".to_string());
+        }
+
+        Ok(config)
+    }
+}
+
+/// 移除值为 null 的字段
+fn strip_nulls(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        JsonValue::Array(arr) => JsonValue::Array(
+            arr.iter().filter(|v| !v.is_null()).map(strip_nulls).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// 读取配置历史快照（已解密）
+pub fn read_history_snapshot(filename: &str) -> Result<JsonValue, String> {
+    if filename.contains('/') || filename.contains('\\') || filename.contains("..") {
+        return Err("无效的备份文件名".to_string());
+    }
+
+    let history_dir = get_config_history_dir().map_err(|e| e.to_string())?;
+    let backup_path = history_dir.join(filename);
+
+    if !backup_path.exists() {
+        return Err(format!("备份文件 {} 不存在", filename));
+    }
+
+    let content = fs::read_to_string(&backup_path)
+        .map_err(|e| format!("读取备份文件失败: {}", e))?;
+
+    let snapshot: JsonValue = serde_json::from_str(&content)
+        .map_err(|e| format!("解析备份文件失败: {}", e))?;
+
+    secrets::decrypt_secrets(&snapshot)
+        .map_err(|e| format!("解密备份文件失败: {:#}", e))
+}
+
+/// 读取待导入的配置文件（已解密）
+fn read_import_file(path: &str, format: Option<&str>) -> Result<(ConfigFormat, JsonValue), String> {
+    let path = PathBuf::from(path);
+    let format = ConfigFormat::detect(format, &path)?;
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("读取导入文件失败: {}", e))?;
+
+    let config = format.deserialize(&content)?;
+    let config = secrets::decrypt_secrets(&config)
+        .map_err(|e| format!("导入文件中的密钥无法解密（可能来自其他设备）: {:#}", e))?;
+
+    Ok((format, config))
+}
+
+/// 导出配置
+/// snapshot 为空时导出当前配置，否则导出指定的历史快照；
+/// 密钥默认替换为占位符，redact_secrets 为 false 时改为使用本地口令加密
+#[tauri::command]
+pub async fn export_config(
+    path: String,
+    format: Option<String>,
+    snapshot: Option<String>,
+    redact_secrets: Option<bool>,
+) -> Result<JsonValue, String> {
+    let target = PathBuf::from(&path);
+    let format = ConfigFormat::detect(format.as_deref(), &target)?;
+
+    let config = match snapshot.as_deref() {
+        Some(filename) => read_history_snapshot(filename)?,
+        None => read_config_file()?,
+    };
+
+    let redact = redact_secrets.unwrap_or(true);
+    let exported = if redact {
+        secrets::redact_secrets(&config)
+    } else {
+        secrets::encrypt_secrets(&config).map_err(|e| format!("加密密钥失败: {:#}", e))?
+    };
+
+    let content = format.serialize(&exported)?;
+
+    fsutil::atomic_write(&target, &content)
+        .map_err(|e| format!("写入导出文件失败: {}", e))?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "path": path,
+        "format": format.as_str(),
+        "redacted": redact,
+        "size": content.len()
+    }))
+}
+
+/// 预览配置导入：返回校验结果以及与当前配置的差异，不做任何写入
+#[tauri::command]
+pub async fn preview_config_import(path: String, format: Option<String>) -> Result<JsonValue, String> {
+    let (format, mut imported) = read_import_file(&path, format.as_deref())?;
+
+    let current = read_config_file().unwrap_or_else(|_| serde_json::json!({}));
+    secrets::unmask_secrets(&mut imported, &current);
+
+    let validation = validate_config_internal(&imported);
+    let mut changes = diff_config_values(&current, &imported);
+    secrets::mask_diff(&mut changes);
+
+    Ok(serde_json::json!({
+        "format": format.as_str(),
+        "valid": validation["valid"],
+        "errors": validation["errors"],
+        "warnings": validation["warnings"],
+        "identical": changes.is_empty(),
+        "changes": changes
+    }))
+}
+
+/// 导入配置：校验通过后创建历史备份并替换当前配置
+/// 导入文件中的占位符密钥会保留当前配置中的原值
+#[tauri::command]
+pub async fn import_config(path: String, format: Option<String>) -> Result<JsonValue, String> {
+    let (_, imported) = read_import_file(&path, format.as_deref())?;
+
+    let config_path = get_config_path_internal().map_err(|e| e.to_string())?;
+
+    if config_path.exists() {
+        update_config(|config| {
+            let mut imported = imported;
+            secrets::unmask_secrets(&mut imported, config);
+            reject_redacted_placeholders(&imported)?;
+            *config = imported;
+            Ok(())
+        })
+    } else {
+        reject_redacted_placeholders(&imported)?;
+        replace_config(&imported)
+    }
+}
+
+/// 导出时脱敏的密钥无法从当前配置还原时拒绝导入，避免把占位符写成密钥
+fn reject_redacted_placeholders(config: &JsonValue) -> Result<(), String> {
+    let paths = secrets::redacted_placeholders(config);
+
+    if paths.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("以下密钥在导出时已脱敏，且无法从当前配置还原，请先填写后再导入: {}", paths.join(", ")))
+    }
+}
+
+/// 配置迁移
+/// nanobot 在版本迭代中重命名或移动过配置字段，每个迁移负责识别并更新一类旧字段。
+/// 迁移按代次顺序排列：第 N 个迁移把第 N 代配置升级到第 N+1 代
+pub struct ConfigMigration {
+    pub id: &'static str,
+    pub description: &'static str,
+    /// 配置中是否存在该迁移需要处理的旧字段
+    detect: fn(&JsonValue) -> bool,
+    apply: fn(&mut JsonValue),
+}
+
+/// 所有已知迁移（按代次排序）
+static MIGRATIONS: &[ConfigMigration] = &[
+    ConfigMigration {
+        id: "exec-restrict-to-workspace",
+        description: "tools.exec.restrictToWorkspace 已移动到 tools.restrictToWorkspace",
+        detect: has_legacy_restrict_to_workspace,
+        apply: move_restrict_to_workspace,
+    },
+];
+
+/// 当前最新的配置代次
+pub const CURRENT_SCHEMA_GENERATION: usize = MIGRATIONS.len() + 1;
+
+/// 检测配置所处的代次：第一个待执行迁移之前的代次，没有待执行迁移时为最新代次
+pub fn detect_schema_generation(config: &JsonValue) -> usize {
+    MIGRATIONS
+        .iter()
+        .position(|m| (m.detect)(config))
+        .map(|index| index + 1)
+        .unwrap_or(CURRENT_SCHEMA_GENERATION)
+}
+
+/// 获取需要执行的迁移
+fn pending_migrations(config: &JsonValue) -> Vec<&'static ConfigMigration> {
+    MIGRATIONS.iter().filter(|m| (m.detect)(config)).collect()
+}
+
+/// 按顺序执行所有待执行的迁移，返回执行过的迁移 ID
+pub fn migrate_config(config: &mut JsonValue) -> Vec<&'static str> {
+    let mut applied = Vec::new();
+
+    for migration in MIGRATIONS {
+        if (migration.detect)(config) {
+            (migration.apply)(config);
+            applied.push(migration.id);
+        }
+    }
+
+    applied
+}
+
+fn has_legacy_restrict_to_workspace(config: &JsonValue) -> bool {
+    config.pointer("/tools/exec/restrictToWorkspace").is_some()
+}
+
+fn move_restrict_to_workspace(config: &mut JsonValue) {
+    let Ok(value) = remove_value_at_pointer(config, "/tools/exec/restrictToWorkspace") else {
+        return;
+    };
+
+    if config.pointer("/tools/restrictToWorkspace").is_none() {
+        let _ = set_value_at_pointer(config, "/tools/restrictToWorkspace", value);
+    }
+}
+
+/// 获取配置迁移状态：当前代次、待执行迁移以及迁移后的差异预览
+#[tauri::command]
+pub async fn get_config_migrations() -> Result<JsonValue, String> {
+    let config = read_config_file()?;
+
+    let pending: Vec<JsonValue> = pending_migrations(&config)
+        .iter()
+        .map(|m| serde_json::json!({
+            "id": m.id,
+            "description": m.description
+        }))
+        .collect();
+
+    let mut migrated = config.clone();
+    migrate_config(&mut migrated);
+
+    let mut changes = diff_config_values(&config, &migrated);
+    secrets::mask_diff(&mut changes);
+
+    Ok(serde_json::json!({
+        "generation": detect_schema_generation(&config),
+        "currentGeneration": CURRENT_SCHEMA_GENERATION,
+        "pending": pending,
+        "changes": changes
+    }))
+}
+
+/// 执行所有待执行的配置迁移（写入前会创建历史备份）
+#[tauri::command]
+pub async fn apply_config_migrations() -> Result<JsonValue, String> {
+    let mut applied = Vec::new();
+
+    let validation = update_config(|config| {
+        applied = migrate_config(config);
+        if applied.is_empty() {
+            return Err("配置已是最新版本，无需迁移".to_string());
+        }
+        Ok(())
+    })?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "applied": applied,
+        "generation": CURRENT_SCHEMA_GENERATION,
+        "validation": validation
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn set_creates_missing_objects() {
+        let mut config = json!({ "agents": { "defaults": { "model": "a" } } });
+
+        set_value_at_pointer(&mut config, "/agents/defaults/model", json!("b")).unwrap();
+        set_value_at_pointer(&mut config, "/channels/telegram/enabled", json!(true)).unwrap();
+
+        assert_eq!(config["agents"]["defaults"]["model"], "b");
+        assert_eq!(config["channels"], json!({ "telegram": { "enabled": true } }));
+    }
+
+    #[test]
+    fn set_handles_escaped_tokens() {
+        let mut config = json!({});
+
+        set_value_at_pointer(&mut config, "/tools/mcpServers/a~1b/env/x~0y", json!("v")).unwrap();
+
+        assert_eq!(config["tools"]["mcpServers"]["a/b"]["env"]["x~y"], "v");
+        assert_eq!(config.pointer("/tools/mcpServers/a~1b/env/x~0y"), Some(&json!("v")));
+    }
+
+    #[test]
+    fn set_updates_and_appends_array_items() {
+        let mut config = json!({ "channels": { "telegram": { "allowFrom": ["a", "b"] } } });
+        let pointer = "/channels/telegram/allowFrom";
+
+        set_value_at_pointer(&mut config, &format!("{}/0", pointer), json!("x")).unwrap();
+        set_value_at_pointer(&mut config, &format!("{}/2", pointer), json!("c")).unwrap();
+        set_value_at_pointer(&mut config, &format!("{}/-", pointer), json!("d")).unwrap();
+        assert_eq!(config.pointer(pointer), Some(&json!(["x", "b", "c", "d"])));
+
+        assert!(set_value_at_pointer(&mut config, &format!("{}/9", pointer), json!("e")).is_err());
+        assert!(set_value_at_pointer(&mut config, &format!("{}/first", pointer), json!("e")).is_err());
+    }
+
+    #[test]
+    fn set_rejects_invalid_paths() {
+        let mut config = json!({ "agents": { "defaults": { "model": "a" } } });
+        let original = config.clone();
+
+        assert!(set_value_at_pointer(&mut config, "agents/defaults", json!({})).is_err());
+        assert!(set_value_at_pointer(&mut config, "/agents/defaults/model/name", json!("b")).is_err());
+        assert!(set_value_at_pointer(&mut config, "", json!([])).is_err());
+        assert_eq!(config, original);
+
+        set_value_at_pointer(&mut config, "", json!({ "providers": {} })).unwrap();
+        assert_eq!(config, json!({ "providers": {} }));
+    }
+
+    #[test]
+    fn remove_returns_old_value() {
+        let mut config = json!({ "tools": { "exec": { "timeout": 3 }, "list": [1, 2, 3] } });
+
+        assert_eq!(remove_value_at_pointer(&mut config, "/tools/exec/timeout").unwrap(), json!(3));
+        assert_eq!(remove_value_at_pointer(&mut config, "/tools/list/1").unwrap(), json!(2));
+        assert_eq!(config, json!({ "tools": { "exec": {}, "list": [1, 3] } }));
+
+        assert!(remove_value_at_pointer(&mut config, "/tools/exec/timeout").is_err());
+        assert!(remove_value_at_pointer(&mut config, "/missing/key").is_err());
+        assert!(remove_value_at_pointer(&mut config, "").is_err());
+    }
+}
@@ -4,6 +4,7 @@ use serde_json::json;
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
+use crate::fsutil::{self, WriteOptions};
 use crate::process;
 
 /// 调度类型
@@ -354,7 +355,8 @@ fn save_jobs_file(jobs_file: &CronJobsFile) -> Result<(), String> {
         .context("序列化 jobs.json 失败")
         .map_err(|e| e.to_string())?;
 
-    fs::write(&path, content)
+    // jobs.json 由 nanobot 读取，写入前保留一份 .bak 以便手动恢复
+    fsutil::atomic_write_with(&path, content, WriteOptions { backup: true, ..Default::default() })
         .context("写入 jobs.json 失败")
         .map_err(|e| e.to_string())?;
 
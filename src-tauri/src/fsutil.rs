@@ -0,0 +1,169 @@
+// 文件写入工具模块
+// 所有 nanoboard 修改的文件都通过这里写入：先写临时文件并 fsync，再 rename 覆盖目标，
+// 避免崩溃或磁盘写满时留下被截断的配置文件
+
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 写入选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// 写入前将原文件复制为 `<文件名>.bak`
+    pub backup: bool,
+    /// 仅所有者可读写（Unix 下为 0600），用于密钥等敏感文件
+    pub private: bool,
+}
+
+/// 原子写入文件（不备份）
+pub fn atomic_write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    atomic_write_with(path, contents, WriteOptions::default())
+}
+
+/// 原子写入文件
+///
+/// 临时文件与目标文件位于同一目录，保证 rename 不跨文件系统；
+/// 目标文件已存在时沿用其权限，避免把 0600 的配置变成全局可读。
+pub fn atomic_write_with(path: &Path, contents: impl AsRef<[u8]>, options: WriteOptions) -> Result<()> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    fs::create_dir_all(&parent)
+        .with_context(|| format!("创建目录 {:?} 失败", parent))?;
+
+    let existing = fs::metadata(path).ok();
+
+    if options.backup && existing.is_some() {
+        let backup = backup_path(path);
+        fs::copy(path, &backup)
+            .with_context(|| format!("备份文件 {:?} 失败", path))?;
+    }
+
+    let tmp_path = temp_path(&parent, path);
+    let permissions = options
+        .private
+        .then(private_permissions)
+        .flatten()
+        .or_else(|| existing.map(|m| m.permissions()));
+    let result = write_and_rename(&tmp_path, path, contents.as_ref(), permissions);
+
+    if result.is_err() {
+        // 清理残留的临时文件，原文件保持不变
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    sync_dir(&parent);
+    Ok(())
+}
+
+/// 仅在目标文件不存在时原子地创建它，已存在时不覆盖，返回是否由本次调用创建
+///
+/// 先写完临时文件再硬链接到目标路径，其他进程不会读到写了一半的内容；
+/// 多个调用者同时创建时只有一个成功，其余应读取已有文件。
+pub fn create_new_with(path: &Path, contents: impl AsRef<[u8]>, options: WriteOptions) -> Result<bool> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    fs::create_dir_all(&parent)
+        .with_context(|| format!("创建目录 {:?} 失败", parent))?;
+
+    let tmp_path = temp_path(&parent, path);
+    let permissions = options.private.then(private_permissions).flatten();
+    let result = write_temp(&tmp_path, contents.as_ref(), permissions).and_then(|_| {
+        match fs::hard_link(&tmp_path, path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("创建文件 {:?} 失败", path)),
+        }
+    });
+
+    let _ = fs::remove_file(&tmp_path);
+    if matches!(result, Ok(true)) {
+        sync_dir(&parent);
+    }
+    result
+}
+
+/// 获取备份文件路径（`<文件名>.bak`）
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+fn temp_path(dir: &Path, target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    dir.join(format!(".{}.{}.{}.tmp", name, std::process::id(), nanos))
+}
+
+fn write_and_rename(
+    tmp_path: &Path,
+    path: &Path,
+    contents: &[u8],
+    permissions: Option<fs::Permissions>,
+) -> Result<()> {
+    write_temp(tmp_path, contents, permissions)?;
+
+    fs::rename(tmp_path, path)
+        .with_context(|| format!("替换文件 {:?} 失败", path))?;
+
+    Ok(())
+}
+
+/// 写入并同步临时文件
+fn write_temp(tmp_path: &Path, contents: &[u8], permissions: Option<fs::Permissions>) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(tmp_path)
+        .with_context(|| format!("创建临时文件 {:?} 失败", tmp_path))?;
+
+    file.write_all(contents)
+        .with_context(|| format!("写入临时文件 {:?} 失败", tmp_path))?;
+
+    if let Some(permissions) = permissions {
+        fs::set_permissions(tmp_path, permissions)
+            .with_context(|| format!("设置文件权限 {:?} 失败", tmp_path))?;
+    }
+
+    file.sync_all()
+        .with_context(|| format!("同步临时文件 {:?} 失败", tmp_path))?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn private_permissions() -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(fs::Permissions::from_mode(0o600))
+}
+
+/// Windows 下用户目录已按账户隔离，沿用默认 ACL
+#[cfg(not(unix))]
+fn private_permissions() -> Option<fs::Permissions> {
+    None
+}
+
+/// 同步目录项，确保 rename 本身落盘（仅 Unix 支持打开目录）
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(d) = fs::File::open(dir) {
+        let _ = d.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
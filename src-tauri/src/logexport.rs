@@ -0,0 +1,202 @@
+// 日志导出模块
+// 使用与日志查询相同的过滤条件，把匹配的记录按时间正序写入文件（纯文本、JSONL 或 CSV）。
+// 从最早的归档开始逐行读取、逐条写出，内存占用与日志大小无关。
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::logfiles::{self, LogFile};
+use crate::logparse::LogRecord;
+use crate::logquery::{CompiledFilter, LogFilter};
+use crate::logredact::Redactor;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 原始日志行（包含堆栈）
+    #[default]
+    Text,
+    /// 每行一条 JSON 记录
+    Jsonl,
+    Csv,
+}
+
+/// 导出参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogExportRequest {
+    #[serde(flatten)]
+    pub filter: LogFilter,
+    /// 目标文件路径
+    pub path: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// 是否对密钥脱敏，默认开启
+    pub redact: Option<bool>,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogExportResult {
+    pub path: String,
+    pub records: usize,
+    pub bytes: u64,
+}
+
+const CSV_HEADER: &str = "timestamp,level,module,function,line,message,traceback,source,stream";
+
+/// CSV 字段转义：包含逗号、引号或换行时加引号，引号加倍
+pub(crate) fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// 写出一条记录
+fn write_record<W: Write>(out: &mut W, record: &LogRecord, format: ExportFormat) -> io::Result<()> {
+    match format {
+        ExportFormat::Text => {
+            for line in record.raw_lines() {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        ExportFormat::Jsonl => {
+            serde_json::to_writer(&mut *out, record)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Csv => {
+            let line = record.line.map(|l| l.to_string()).unwrap_or_default();
+            let traceback = record.traceback.join("\n");
+            let fields = [
+                record.timestamp.as_deref().unwrap_or(""),
+                record.level.as_str(),
+                record.module.as_deref().unwrap_or(""),
+                record.function.as_deref().unwrap_or(""),
+                &line,
+                &record.message,
+                &traceback,
+                record.source.as_deref().unwrap_or(""),
+                record.stream.as_deref().unwrap_or(""),
+            ];
+            let row: Vec<Cow<str>> = fields.iter().map(|f| csv_field(f)).collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+    }
+    Ok(())
+}
+
+/// 正向读取一个日志文件，最多读取 limit 字节（当前日志只读到导出开始时的大小）
+fn export_file<W: Write>(
+    file: &LogFile,
+    limit: u64,
+    filter: &CompiledFilter,
+    redactor: Option<&Redactor>,
+    format: ExportFormat,
+    out: &mut W,
+) -> Result<usize, String> {
+    let mut count = 0;
+
+    logfiles::read_records(file, limit, |mut record| {
+        if !filter.matches(&record) {
+            return Ok(());
+        }
+
+        if let Some(redactor) = redactor {
+            redactor.redact_record(&mut record);
+        }
+
+        write_record(out, &record, format).map_err(|e| format!("写入导出文件失败: {}", e))?;
+        count += 1;
+        Ok(())
+    })?;
+
+    Ok(count)
+}
+
+/// 导出日志，files 按从新到旧排列
+pub fn export_files(files: &[LogFile], request: &LogExportRequest) -> Result<LogExportResult, String> {
+    if request.path.trim().is_empty() {
+        return Err("导出路径不能为空".to_string());
+    }
+
+    let filter = request.filter.compile()?;
+    let target = PathBuf::from(request.path.trim());
+
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+
+    let redactor = request.redact.unwrap_or(true).then(Redactor::from_config);
+
+    // 先写入临时文件，完成后再改名，避免留下不完整的导出文件
+    let tmp = part_path(&target);
+    let result = (|| -> Result<usize, String> {
+        let file = File::create(&tmp)
+            .map_err(|e| format!("创建导出文件失败: {}", e))?;
+        let mut out = BufWriter::new(file);
+
+        if request.format == ExportFormat::Csv {
+            writeln!(out, "{}", CSV_HEADER).map_err(|e| format!("写入导出文件失败: {}", e))?;
+        }
+
+        let mut count = 0;
+        // 从最早的归档开始，保证输出按时间正序
+        for file in files.iter().rev() {
+            let limit = if file.current { file.size } else { u64::MAX };
+            count += export_file(file, limit, &filter, redactor.as_ref(), request.format, &mut out)?;
+        }
+
+        out.into_inner()
+            .map_err(|e| format!("写入导出文件失败: {}", e.error()))?
+            .sync_all()
+            .map_err(|e| format!("写入导出文件失败: {}", e))?;
+
+        Ok(count)
+    })();
+
+    let records = match result {
+        Ok(count) => count,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+
+    fs::rename(&tmp, &target).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("保存导出文件失败: {}", e)
+    })?;
+
+    let bytes = fs::metadata(&target).map(|m| m.len()).unwrap_or(0);
+
+    Ok(LogExportResult {
+        path: target.to_string_lossy().to_string(),
+        records,
+        bytes,
+    })
+}
+
+/// 导出过程中使用的临时文件 <文件名>.part
+fn part_path(target: &Path) -> PathBuf {
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    target.with_file_name(format!("{}.part", name))
+}
+
+/// 导出日志（包括轮转出的归档）到指定文件
+#[tauri::command]
+pub async fn export_logs(request: LogExportRequest) -> Result<LogExportResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let files = logfiles::discover_log_files()?;
+        export_files(&files, &request)
+    })
+    .await
+    .map_err(|e| format!("导出日志失败: {}", e))?
+}
@@ -0,0 +1,286 @@
+// 日志文件发现模块
+// 在日志目录中查找当前日志及其轮转出的归档（nanobot.log.1、带日期的文件、.gz 压缩包），
+// 压缩归档按需解压到缓存目录，供查询模块按块读取
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::logger;
+use crate::logparse::{LogParser, LogRecord};
+
+/// 用于识别文件的头部字节数
+pub const HEAD_BYTES: usize = 256;
+
+/// 日志文件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: u64,
+    /// gzip 压缩的归档
+    pub compressed: bool,
+    /// 正在写入的当前日志
+    pub current: bool,
+}
+
+/// 判断文件名是否为当前日志的轮转归档
+/// 例如 nanobot.log.1、nanobot.log.2.gz、nanobot.log.2025-01-01、nanobot.2025-01-01_12-00-00_000000.log
+fn is_archive_name(name: &str, current: &str) -> bool {
+    let stem = current.strip_suffix(".log").unwrap_or(current);
+
+    if name == current || name.starts_with('.') {
+        return false;
+    }
+
+    if name.ends_with(".tmp") || name.ends_with(".lock") {
+        return false;
+    }
+
+    let Some(rest) = name.strip_prefix(stem) else {
+        return false;
+    };
+
+    rest.starts_with(['.', '_', '-']) && rest.contains(".log")
+}
+
+/// 列出当前日志及所有归档，按从新到旧排序（当前日志总在最前）
+pub fn discover_log_files() -> Result<Vec<LogFile>, String> {
+    let current_path = logger::get_log_path().map_err(|e| e.to_string())?;
+    let current_name = current_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let Some(dir) = current_path.parent() else {
+        return Ok(Vec::new());
+    };
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("读取日志目录失败: {}", e))?;
+
+    let mut files = Vec::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let current = name == current_name;
+
+        if !current && !is_archive_name(&name, &current_name) {
+            continue;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata.modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        files.push(LogFile {
+            compressed: name.ends_with(".gz"),
+            path: entry.path(),
+            name,
+            size: metadata.len(),
+            modified,
+            current,
+        });
+    }
+
+    // 命名方式各不相同，按修改时间排序最可靠；同一时间的按编号从小到大（.1 比 .2 新）
+    files.sort_by(|a, b| {
+        b.current
+            .cmp(&a.current)
+            .then(b.modified.cmp(&a.modified))
+            .then(archive_index(&a.name).cmp(&archive_index(&b.name)))
+            .then(b.name.cmp(&a.name))
+    });
+
+    Ok(files)
+}
+
+/// 提取 nanobot.log.N 中的编号
+fn archive_index(name: &str) -> u64 {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    name.rsplit('.').next().and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+/// 读取文件开头的内容（压缩归档读取解压后的内容）
+pub fn read_head(path: &Path, compressed: bool) -> io::Result<Vec<u8>> {
+    let file = File::open(path)?;
+    let mut head = Vec::with_capacity(HEAD_BYTES);
+
+    if compressed {
+        GzDecoder::new(file).take(HEAD_BYTES as u64).read_to_end(&mut head)?;
+    } else {
+        file.take(HEAD_BYTES as u64).read_to_end(&mut head)?;
+    }
+
+    Ok(head)
+}
+
+/// 判断两个文件头是否来自同一个文件（较短的一方是另一方的前缀）
+pub fn same_head(a: &[u8], b: &[u8]) -> bool {
+    let common = a.len().min(b.len());
+    a[..common] == b[..common]
+}
+
+/// 文件指纹，文件被轮转改名后仍保持不变，用于查询游标
+pub fn fingerprint(file: &LogFile) -> io::Result<String> {
+    let head = read_head(&file.path, file.compressed)?;
+    let digest = Sha256::digest(&head);
+    Ok(digest[..8].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 查找头部与指定内容一致的归档（当前日志被轮转后，旧内容所在的文件）
+pub fn find_archive_by_head(head: &[u8]) -> Option<LogFile> {
+    if head.is_empty() {
+        return None;
+    }
+
+    discover_log_files()
+        .ok()?
+        .into_iter()
+        .filter(|f| !f.current)
+        .find(|f| read_head(&f.path, f.compressed).map(|h| !h.is_empty() && same_head(&h, head)).unwrap_or(false))
+}
+
+/// 获取解压缓存目录
+fn get_cache_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法找到用户主目录".to_string())?;
+    Ok(home.join(".nanobot").join("cache").join("logs"))
+}
+
+/// 清理解压缓存：base 归档的其他版本，以及源归档已不存在的条目（保留 keep）
+/// 缓存文件名为 <归档名去掉 .gz>.<大小>-<修改时间>
+fn prune_cache(cache_dir: &Path, log_dir: &Path, base: &str, keep: &Path) {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // 正在解压的临时文件，或其他查询刚生成的同一版本
+        if name.starts_with('.') || entry.path() == keep {
+            continue;
+        }
+
+        let Some((source, _)) = name.rsplit_once('.') else {
+            continue;
+        };
+
+        if source == base || !log_dir.join(format!("{}.gz", source)).exists() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// 返回可以按块随机读取的路径，压缩归档会先解压到缓存目录
+pub fn readable_path(file: &LogFile) -> Result<PathBuf, String> {
+    if !file.compressed {
+        return Ok(file.path.clone());
+    }
+
+    let base = file.name.strip_suffix(".gz").unwrap_or(&file.name);
+    let cache_dir = get_cache_dir()?;
+    let cached = cache_dir.join(format!("{}.{}-{}", base, file.size, file.modified));
+
+    if cached.exists() {
+        return Ok(cached);
+    }
+
+    fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("创建缓存目录失败: {}", e))?;
+
+    // 同一归档的旧缓存已失效，对应归档已被删除的缓存也一并清理
+    if let Some(log_dir) = file.path.parent() {
+        prune_cache(&cache_dir, log_dir, base, &cached);
+    }
+
+    // 同一进程中可能同时有多个查询或导出在解压，临时文件名需要唯一
+    static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let tmp = cache_dir.join(format!(
+        ".{}.{}.{}.tmp",
+        base,
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = (|| -> io::Result<()> {
+        let mut decoder = GzDecoder::new(File::open(&file.path)?);
+        let mut out = File::create(&tmp)?;
+        io::copy(&mut decoder, &mut out)?;
+        fs::rename(&tmp, &cached)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("解压日志归档 {} 失败: {}", file.name, e));
+    }
+
+    Ok(cached)
+}
+
+/// 从头正向读取一个日志文件中的记录，最多读取 limit 字节；记录的 source 为文件名
+pub fn read_records<F>(file: &LogFile, limit: u64, mut f: F) -> Result<(), String>
+where
+    F: FnMut(LogRecord) -> Result<(), String>,
+{
+    let path = readable_path(file)?;
+    let reader = File::open(&path)
+        .map_err(|e| format!("打开日志文件 {} 失败: {}", file.name, e))?;
+    let mut reader = BufReader::new(reader.take(limit));
+
+    let mut parser = LogParser::new();
+    let mut buf = Vec::new();
+    let mut offset = 0u64;
+
+    let mut emit = |mut record: LogRecord| {
+        record.source = Some(file.name.clone());
+        f(record)
+    };
+
+    loop {
+        buf.clear();
+        let n = reader
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| format!("读取日志文件 {} 失败: {}", file.name, e))?;
+        if n == 0 {
+            break;
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+        if let Some(record) = parser.push_at(&line, Some(offset)) {
+            emit(record)?;
+        }
+        offset += n as u64;
+    }
+
+    if let Some(record) = parser.flush() {
+        emit(record)?;
+    }
+
+    Ok(())
+}
+
+/// 列出日志文件（当前日志和归档）
+#[tauri::command]
+pub async fn list_log_files() -> Result<Vec<LogFile>, String> {
+    discover_log_files()
+}
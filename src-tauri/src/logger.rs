@@ -2,73 +2,45 @@ use anyhow::{Context, Result};
 use dirs::home_dir;
 use serde_json::json;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
-use std::sync::Arc;
-use tauri::Emitter;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::logfiles;
+use crate::logparse;
+use crate::logquery::BackwardLineReader;
+use crate::logredact;
+use crate::logstats;
 
 /// 文件位置跟踪器
 pub struct FileTracker {
     log_path: PathBuf,
     position: u64,
+    /// 文件开头的内容，用于识别日志是否被轮转替换
+    head: Vec<u8>,
 }
 
 impl FileTracker {
-    pub fn new() -> Self {
+    /// 从文件当前末尾开始跟踪
+    pub fn at_end(log_path: &Path) -> Self {
         Self {
-            log_path: PathBuf::new(),
-            position: 0,
+            log_path: log_path.to_path_buf(),
+            position: std::fs::metadata(log_path).map(|m| m.len()).unwrap_or(0),
+            head: logfiles::read_head(log_path, false).unwrap_or_default(),
         }
     }
 }
 
-/// Watcher 任务句柄，用于取消监控任务
-#[derive(Clone)]
-pub struct WatcherHandle {
-    running: Arc<std::sync::atomic::AtomicBool>,
-    task_handle: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
-}
-
-impl WatcherHandle {
-    pub fn new() -> Self {
-        Self {
-            running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            task_handle: Arc::new(tokio::sync::Mutex::new(None)),
-        }
-    }
-
-    pub fn is_running(&self) -> bool {
-        self.running.load(std::sync::atomic::Ordering::Relaxed)
-    }
-
-    pub fn set_running(&self, running: bool) {
-        self.running.store(running, std::sync::atomic::Ordering::Relaxed);
-    }
-
-    pub async fn set_task_handle(&self, handle: tokio::task::JoinHandle<()>) {
-        let mut task = self.task_handle.lock().await;
-        *task = Some(handle);
-    }
-
-    pub async fn abort(&self) {
-        let mut task = self.task_handle.lock().await;
-        if let Some(handle) = task.take() {
-            handle.abort();
-        }
-        self.set_running(false);
-    }
-}
-
 /// 获取日志文件路径
-fn get_log_path() -> Result<PathBuf> {
+pub(crate) fn get_log_path() -> Result<PathBuf> {
     let home = home_dir().context("无法找到用户主目录")?;
     let log_path = home.join(".nanobot").join("logs").join("nanobot.log");
     Ok(log_path)
 }
 
-/// 获取最近的日志（优化版：只保留最后 N 行，避免大文件内存问题）
+/// 获取最近的日志
+/// lines 为返回的行数，从文件末尾向前按块读取，不必把整个文件读入内存
 #[tauri::command]
-pub async fn get_logs(lines: Option<usize>) -> Result<serde_json::Value, String> {
+pub async fn get_logs(window: tauri::Window, lines: Option<usize>) -> Result<serde_json::Value, String> {
     let log_path = get_log_path().map_err(|e| e.to_string())?;
     let line_count = lines.unwrap_or(100);
 
@@ -79,296 +51,173 @@ pub async fn get_logs(lines: Option<usize>) -> Result<serde_json::Value, String>
         }));
     }
 
-    let file = File::open(&log_path)
-        .map_err(|e| format!("打开日志文件失败: {}", e))?;
-
-    let reader = BufReader::new(file);
+    let (mut logs, has_more) = tokio::task::spawn_blocking(move || -> std::io::Result<(Vec<String>, bool)> {
+        let mut reader = BackwardLineReader::open(&log_path, None)?;
+        let mut logs = Vec::new();
 
-    // 使用环形缓冲区，只保留最后 N 行，避免将整个文件读入内存
-    let mut total_lines = 0usize;
-    let mut ring_buffer: Vec<String> = Vec::with_capacity(line_count);
-
-    for line in reader.lines().map_while(Result::ok) {
-        total_lines += 1;
-        if ring_buffer.len() < line_count {
-            ring_buffer.push(line);
-        } else {
-            // 环形缓冲区已满，移除最旧的行
-            ring_buffer.remove(0);
-            ring_buffer.push(line);
+        while logs.len() < line_count {
+            match reader.next_line()? {
+                Some((_, line)) => logs.push(line),
+                None => break,
+            }
+        }
+        logs.reverse();
+
+        // 还能再读到一行说明没有读到文件开头
+        let has_more = logs.len() == line_count && reader.next_line()?.is_some();
+        Ok((logs, has_more))
+    })
+    .await
+    .map_err(|e| format!("读取日志失败: {}", e))?
+    .map_err(|e| format!("读取日志失败: {}", e))?;
+
+    // 总数取增量统计缓存中的记录数，不再为此扫描整个文件
+    let total = tokio::task::spawn_blocking(logstats::level_counts)
+        .await
+        .ok()
+        .and_then(|counts| counts.ok())
+        .map(|counts| counts.total)
+        .unwrap_or(logs.len());
+
+    let mut records = logparse::parse_lines(&logs);
+
+    if let Some(redactor) = logredact::for_display(window.label()) {
+        for line in logs.iter_mut() {
+            *line = redactor.redact(line).into_owned();
+        }
+        for record in records.iter_mut() {
+            redactor.redact_record(record);
         }
     }
 
     Ok(json!({
-        "logs": ring_buffer,
-        "total": total_lines,
-        "showing": ring_buffer.len()
+        "logs": logs,
+        "records": records,
+        "total": total,
+        "showing": logs.len(),
+        "hasMore": has_more
     }))
 }
 
-/// 读取新增的日志行（从上次位置开始）
-fn read_new_lines(log_path: &PathBuf, last_pos: &mut u64) -> Result<Vec<String>, String> {
-    let mut file = File::open(log_path)
-        .map_err(|e| format!("打开日志文件失败: {}", e))?;
+/// 从 position 开始读取完整的行，最后一行未写完时留到下次
+fn read_complete_lines(path: &Path, position: &mut u64, lines: &mut Vec<String>) -> std::io::Result<()> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(*position))?;
 
-    // 获取当前文件大小
-    let current_size = file.metadata()
-        .map(|m| m.len())
-        .unwrap_or(0);
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
 
-    // 如果文件被截断（例如日志轮转），重置位置
-    if current_size < *last_pos {
-        *last_pos = 0;
-    }
+    loop {
+        buf.clear();
+        let n = reader.read_until(b'\n', &mut buf)?;
+        if n == 0 || buf.last() != Some(&b'\n') {
+            break;
+        }
 
-    // 如果没有新内容，返回空
-    if current_size == *last_pos {
-        return Ok(Vec::new());
+        *position += n as u64;
+        lines.push(String::from_utf8_lossy(&buf).trim_end_matches(['\r', '\n']).to_string());
     }
 
-    // 定位到上次读取的位置
-    file.seek(SeekFrom::Start(*last_pos))
-        .map_err(|e| format!("定位文件位置失败: {}", e))?;
+    Ok(())
+}
 
-    let reader = BufReader::new(file);
+/// 读取新增的日志行（从上次位置开始）
+/// 日志被轮转（改名后新建）或截断时，先从旧内容所在的归档读完剩余部分，再从新文件开头读取
+pub(crate) fn read_new_lines(tracker: &mut FileTracker) -> Result<Vec<String>, String> {
+    let log_path = tracker.log_path.clone();
     let mut new_lines = Vec::new();
 
-    for log_line in reader.lines().map_while(Result::ok) {
-        new_lines.push(log_line);
-    }
+    // 轮转过程中当前文件可能暂时不存在
+    let Ok(metadata) = std::fs::metadata(&log_path) else {
+        return Ok(new_lines);
+    };
 
-    // 更新位置为当前文件大小
-    *last_pos = current_size;
+    let current_size = metadata.len();
+    let head = logfiles::read_head(&log_path, false)
+        .map_err(|e| format!("打开日志文件失败: {}", e))?;
 
-    Ok(new_lines)
-}
+    let replaced = !logfiles::same_head(&head, &tracker.head);
 
-/// 启动日志流
-#[tauri::command]
-pub async fn start_log_stream(
-    window: tauri::Window,
-    file_tracker: tauri::State<'_, Arc<tokio::sync::Mutex<FileTracker>>>,
-    watcher_handle: tauri::State<'_, Arc<WatcherHandle>>,
-) -> Result<(), String> {
-    // 检查是否已经在运行
-    if watcher_handle.is_running() {
-        return Ok(()); // 已经在运行，直接返回
+    if current_size < tracker.position || replaced {
+        if tracker.position > 0 {
+            if let Some(archive) = logfiles::find_archive_by_head(&tracker.head) {
+                let mut position = tracker.position;
+                let path = logfiles::readable_path(&archive)?;
+                read_complete_lines(&path, &mut position, &mut new_lines)
+                    .map_err(|e| format!("读取日志归档失败: {}", e))?;
+            }
+        }
+
+        tracker.position = 0;
+        tracker.head.clear();
     }
 
-    watcher_handle.set_running(true);
-    let log_path = get_log_path().map_err(|e| e.to_string())?;
+    if head.len() > tracker.head.len() {
+        tracker.head = head;
+    }
 
-    // 如果日志文件不存在，创建它
-    if !log_path.exists() {
-        // 确保目录存在
-        if let Some(parent) = log_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("创建日志目录失败: {}", e))?;
-        }
-        // 创建空日志文件
-        std::fs::File::create(&log_path)
-            .map_err(|e| format!("创建日志文件失败: {}", e))?;
+    // 如果没有新内容，返回空
+    if current_size == tracker.position {
+        return Ok(new_lines);
     }
 
-    // 获取当前文件大小作为起始位置
-    let file_size = std::fs::metadata(&log_path)
-        .map(|m| m.len())
-        .unwrap_or(0);
+    read_complete_lines(&log_path, &mut tracker.position, &mut new_lines)
+        .map_err(|e| format!("读取日志文件失败: {}", e))?;
 
-    // 初始化文件位置跟踪
-    {
-        let mut tracker = file_tracker.lock().await;
-        tracker.log_path = log_path.clone();
-        tracker.position = file_size;
-    }
+    Ok(new_lines)
+}
 
-    // 使用notify监控日志文件变化
-    let log_path_clone = log_path.clone();
-    let window_clone = window.clone();
-
-    // 克隆 file_tracker 的 Arc，这样可以在闭包中使用
-    let file_tracker_arc = file_tracker.inner().clone();
-
-    let task = tokio::spawn(async move {
-        use notify::{Watcher, RecursiveMode, EventKind, Result as NotifyResult, recommended_watcher};
-        use std::time::Duration;
-
-        let log_path_for_watch = log_path_clone.clone();
-        // 为轮询再克隆一次变量
-        let log_path_for_poll = log_path_clone.clone();
-        let file_tracker_for_poll = file_tracker_arc.clone();
-        let window_for_poll = window_clone.clone();
-
-        // 创建 watcher，同时使用轮询作为备选方案
-        let watcher_result = recommended_watcher(move |res: NotifyResult<notify::Event>| {
-            match res {
-                Ok(event) => {
-                    if matches!(event.kind, EventKind::Modify(_)) {
-                        // 使用 try_lock
-                        let mut tracker = match file_tracker_arc.try_lock() {
-                            Ok(guard) => guard,
-                            Err(_) => {
-                                eprintln!("无法获取锁，跳过此次更新");
-                                return;
-                            }
-                        };
-
-                        let last_pos = &mut tracker.position;
-                        match read_new_lines(&log_path_clone, last_pos) {
-                            Ok(new_lines) => {
-                                if !new_lines.is_empty() {
-                                    eprintln!("读取到 {} 行新日志", new_lines.len());
-                                    match window_clone.emit("log-update", new_lines) {
-                                        Ok(_) => {
-                                            eprintln!("成功发送日志更新事件");
-                                        }
-                                        Err(e) => {
-                                            eprintln!("发送事件失败: {:?}", e);
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("读取新行失败: {}", e);
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("监控错误: {:?}", e);
-                }
-            }
-        }).map_err(|e| format!("创建文件监控器失败: {}", e));
+/// 读取日志文件末尾的若干字节并按行返回（丢弃被截断的首行）
+/// 用于从最近的 gateway 日志中推断运行状态，避免读取整个大文件
+pub fn read_recent_lines(max_bytes: u64) -> Result<Vec<String>, String> {
+    let log_path = get_log_path().map_err(|e| e.to_string())?;
+    read_tail_lines(&log_path, max_bytes)
+}
 
-        if let Err(ref e) = watcher_result {
-            eprintln!("Watcher 创建失败: {}", e);
-            return;
-        }
+/// 读取指定文件末尾的若干字节并按行返回（丢弃被截断的首行），文件不存在时返回空
+pub fn read_tail_lines(path: &Path, max_bytes: u64) -> Result<Vec<String>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
 
-        let mut watcher = watcher_result.unwrap();
+    let mut file = File::open(path)
+        .map_err(|e| format!("打开日志文件失败: {}", e))?;
 
-        // 开始监控
-        if let Err(e) = watcher.watch(&log_path_for_watch, RecursiveMode::NonRecursive) {
-            eprintln!("监控日志文件失败: {}", e);
-            return;
-        }
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let start = size.saturating_sub(max_bytes);
 
-        // 同时使用轮询作为备选方案，防止 watcher 漏掉某些事件
-        // 减少轮询频率以降低 CPU 使用率（从 500ms 增加到 2000ms）
-        loop {
-            tokio::time::sleep(Duration::from_millis(2000)).await;
-
-            // 定期检查文件大小是否有变化
-            if let Ok(metadata) = std::fs::metadata(&log_path_for_poll) {
-                let current_size = metadata.len();
-
-                // 尝试获取 tracker 检查是否有新内容
-                if let Ok(mut tracker) = file_tracker_for_poll.try_lock() {
-                    if current_size > tracker.position {
-                        // 有新内容，读取新行
-                        let last_pos = &mut tracker.position;
-                        match read_new_lines(&log_path_for_poll, last_pos) {
-                            Ok(new_lines) => {
-                                if !new_lines.is_empty() {
-                                    match window_for_poll.emit("log-update", new_lines) {
-                                        Ok(_) => {}
-                                        Err(e) => {
-                                            eprintln!("轮询发送事件失败: {:?}", e);
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("轮询读取新行失败: {}", e);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    });
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("定位文件位置失败: {}", e))?;
 
-    // 保存任务句柄
-    watcher_handle.set_task_handle(task).await;
+    // 起始位置可能落在多字节字符中间，按字节读取后再做有损转换
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)
+        .map_err(|e| format!("读取日志文件失败: {}", e))?;
 
-    Ok(())
-}
+    let content = String::from_utf8_lossy(&buf);
+    let skip = if start > 0 { 1 } else { 0 };
 
-/// 停止日志流
-#[tauri::command]
-pub async fn stop_log_stream(
-    watcher_handle: tauri::State<'_, Arc<WatcherHandle>>,
-) -> Result<(), String> {
-    if watcher_handle.is_running() {
-        watcher_handle.abort().await;
-        Ok(())
-    } else {
-        Err("没有正在运行的日志监控".to_string())
-    }
+    Ok(content.lines().skip(skip).map(|l| l.to_string()).collect())
 }
 
 /// 获取日志统计信息（内部函数，不需要 #[tauri::command]）
-pub fn get_log_statistics_internal() -> Result<serde_json::Value, String> {
-    let log_path = get_log_path().map_err(|e| e.to_string())?;
-
-    if !log_path.exists() {
-        return Ok(json!({
-            "total": 0,
-            "debug": 0,
-            "info": 0,
-            "warn": 0,
-            "error": 0,
-        }));
-    }
-
-    let file = File::open(&log_path)
-        .map_err(|e| format!("打开日志文件失败: {}", e))?;
-
-    let reader = BufReader::new(file);
-    let logs: Vec<String> = reader.lines()
-        .map_while(Result::ok)
-        .collect();
-
-    let mut debug = 0usize;
-    let mut info = 0usize;
-    let mut warn = 0usize;
-    let mut error = 0usize;
-
-    for log in &logs {
-        let log_upper = log.to_uppercase();
-        // 匹配 "| DEBUG |" 格式（前后可能有空格）
-        if log_upper.contains("DEBUG") {
-            debug += 1;
-        } else if log_upper.contains("INFO") {
-            info += 1;
-        } else if log_upper.contains("WARNING") || log_upper.contains("WARN") {
-            warn += 1;
-        } else if log_upper.contains("ERROR") {
-            error += 1;
-        }
-    }
-
-    let total = logs.len();
+/// 统计结果会被缓存，每次只解析上次之后新增的内容；首次调用需要扫描整个文件，放到阻塞线程中执行
+pub async fn get_log_statistics_internal() -> Result<serde_json::Value, String> {
+    let counts = tokio::task::spawn_blocking(logstats::level_counts)
+        .await
+        .map_err(|e| format!("获取日志统计失败: {}", e))??;
 
     Ok(json!({
-        "total": total,
-        "debug": debug,
-        "info": info,
-        "warn": warn,
-        "error": error,
+        "total": counts.total,
+        "debug": counts.debug,
+        "info": counts.info,
+        "warn": counts.warn,
+        "error": counts.error,
     }))
 }
 
 /// 获取日志统计信息
 #[tauri::command]
 pub async fn get_log_statistics() -> Result<serde_json::Value, String> {
-    get_log_statistics_internal()
-}
-
-/// 检查日志流是否正在运行
-#[tauri::command]
-pub async fn is_log_stream_running(
-    watcher_handle: tauri::State<'_, Arc<WatcherHandle>>,
-) -> Result<bool, String> {
-    Ok(watcher_handle.is_running())
+    get_log_statistics_internal().await
 }
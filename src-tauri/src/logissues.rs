@@ -0,0 +1,307 @@
+// 错误分组模块
+// 把错误记录中的数字、ID、路径等可变部分替换为占位符后计算指纹，
+// 相同指纹的记录归为一组，提供按问题聚合的视图
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::logfiles::{self, LogFile};
+use crate::logparse::{LogLevel, LogRecord};
+use crate::logquery::{self, LogFilter, LogQuery};
+use crate::logredact;
+
+/// 每页读取的记录数
+const PAGE_SIZE: usize = 5000;
+
+/// 默认最多分析的记录数
+const DEFAULT_MAX_RECORDS: usize = 50_000;
+
+/// 默认最多扫描的字节数
+const DEFAULT_MAX_SCAN_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 默认返回的分组数
+const DEFAULT_GROUP_LIMIT: usize = 100;
+
+/// 分组参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorGroupQuery {
+    /// 过滤条件，未指定级别时只分析 ERROR 及以上
+    #[serde(flatten)]
+    pub filter: LogFilter,
+    pub max_records: Option<usize>,
+    pub max_scan_bytes: Option<u64>,
+    /// 返回的分组数
+    pub limit: Option<usize>,
+}
+
+/// 一组相同的错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorGroup {
+    pub fingerprint: String,
+    /// 组内最高级别
+    pub level: LogLevel,
+    pub module: Option<String>,
+    pub function: Option<String>,
+    /// 归一化后的消息
+    pub title: String,
+    /// 归一化后的异常行
+    pub exception: Option<String>,
+    pub count: usize,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    /// 最近一次出现的完整记录（包含堆栈）
+    pub sample: LogRecord,
+}
+
+/// 分组结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorGroupResult {
+    /// 按出现次数从多到少排列
+    pub groups: Vec<ErrorGroup>,
+    /// 分组前的分组总数
+    pub total_groups: usize,
+    /// 参与分组的记录数
+    pub total_records: usize,
+    pub scanned_bytes: u64,
+    /// 达到记录数或扫描量上限，更早的日志未分析
+    pub truncated: bool,
+}
+
+/// 归一化规则，按顺序替换
+fn normalize_rules() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+
+    RULES.get_or_init(|| {
+        [
+            (r"[a-zA-Z][a-zA-Z0-9+.\-]*://[^\s'\x22<>]+", "<url>"),
+            (r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b", "<uuid>"),
+            (r"\b0x[0-9a-fA-F]+\b", "<hex>"),
+            (r"(?:[A-Za-z]:)?(?:[\\/][\w.\-@~]+){2,}[\\/]?", "<path>"),
+        ]
+        .into_iter()
+        .filter_map(|(pattern, replacement)| Regex::new(pattern).ok().map(|r| (r, replacement)))
+        .collect()
+    })
+}
+
+/// 替换数字、ID、路径等可变部分
+pub fn normalize(text: &str) -> String {
+    static HEX_ID: OnceLock<Regex> = OnceLock::new();
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    static SPACES: OnceLock<Regex> = OnceLock::new();
+
+    let mut text = text.to_string();
+
+    for (regex, replacement) in normalize_rules() {
+        text = regex.replace_all(&text, *replacement).into_owned();
+    }
+
+    // 8 位以上同时含数字和字母的十六进制串视为 ID，纯字母的（如 "deadline"）是普通单词
+    let hex_id = HEX_ID.get_or_init(|| Regex::new(r"\b[0-9a-fA-F]{8,}\b").unwrap());
+    text = hex_id
+        .replace_all(&text, |caps: &regex::Captures| {
+            let m = &caps[0];
+            if m.bytes().any(|b| b.is_ascii_digit()) && m.bytes().any(|b| b.is_ascii_alphabetic()) {
+                "<hex>".to_string()
+            } else {
+                m.to_string()
+            }
+        })
+        .into_owned();
+
+    let number = NUMBER.get_or_init(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+    text = number.replace_all(&text, "<n>").into_owned();
+
+    let spaces = SPACES.get_or_init(|| Regex::new(r"\s+").unwrap());
+    spaces.replace_all(text.trim(), " ").into_owned()
+}
+
+/// 计算记录的指纹：模块、函数、归一化后的消息和异常行
+pub fn fingerprint(record: &LogRecord) -> (String, String, Option<String>) {
+    let title = normalize(&record.message);
+    let exception = record.exception_line().map(normalize);
+
+    let mut hasher = Sha256::new();
+    for part in [
+        record.module.as_deref().unwrap_or(""),
+        record.function.as_deref().unwrap_or(""),
+        &title,
+        exception.as_deref().unwrap_or(""),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+
+    let digest = hasher.finalize();
+    let fingerprint = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    (fingerprint, title, exception)
+}
+
+/// 在日志文件上分组，files 按从新到旧排列
+pub fn group_errors(files: &[LogFile], query: &ErrorGroupQuery) -> Result<ErrorGroupResult, String> {
+    let mut filter = query.filter.clone();
+    if filter.levels.is_none() && filter.min_level.is_none() {
+        filter.min_level = Some(LogLevel::Error);
+    }
+
+    let max_records = query.max_records.unwrap_or(DEFAULT_MAX_RECORDS).max(1);
+    let max_scan = query.max_scan_bytes.unwrap_or(DEFAULT_MAX_SCAN_BYTES);
+    let limit = query.limit.unwrap_or(DEFAULT_GROUP_LIMIT).max(1);
+
+    let mut groups: HashMap<String, ErrorGroup> = HashMap::new();
+    let mut total_records = 0;
+    let mut scanned = 0u64;
+    let mut cursor = None;
+    let mut truncated = false;
+
+    loop {
+        let page = logquery::query_files(files, &LogQuery {
+            filter: filter.clone(),
+            cursor: cursor.take(),
+            limit: Some(PAGE_SIZE.min(max_records - total_records)),
+            max_scan_bytes: Some(max_scan.saturating_sub(scanned)),
+        })?;
+
+        scanned += page.scanned_bytes;
+        total_records += page.records.len();
+
+        // 页内按时间正序，整体从新到旧
+        for record in page.records.into_iter().rev() {
+            let (fingerprint, title, exception) = fingerprint(&record);
+
+            match groups.get_mut(&fingerprint) {
+                Some(group) => {
+                    group.count += 1;
+                    group.level = group.level.max(record.level);
+                    if record.timestamp.is_some() {
+                        group.first_seen = record.timestamp.clone();
+                    }
+                }
+                None => {
+                    groups.insert(fingerprint.clone(), ErrorGroup {
+                        fingerprint,
+                        level: record.level,
+                        module: record.module.clone(),
+                        function: record.function.clone(),
+                        title,
+                        exception,
+                        count: 1,
+                        first_seen: record.timestamp.clone(),
+                        last_seen: record.timestamp.clone(),
+                        sample: record,
+                    });
+                }
+            }
+        }
+
+        if !page.has_more {
+            break;
+        }
+
+        if total_records >= max_records || scanned >= max_scan {
+            truncated = true;
+            break;
+        }
+
+        cursor = page.next_cursor;
+    }
+
+    let mut groups: Vec<ErrorGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then(b.last_seen.cmp(&a.last_seen)));
+
+    let total_groups = groups.len();
+    groups.truncate(limit);
+
+    Ok(ErrorGroupResult {
+        groups,
+        total_groups,
+        total_records,
+        scanned_bytes: scanned,
+        truncated,
+    })
+}
+
+/// 按指纹对错误日志分组（包括轮转出的归档）
+#[tauri::command]
+pub async fn get_error_groups(window: tauri::Window, query: Option<ErrorGroupQuery>) -> Result<ErrorGroupResult, String> {
+    let query = query.unwrap_or_default();
+    let label = window.label().to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let files = logfiles::discover_log_files()?;
+        let mut result = group_errors(&files, &query)?;
+
+        if let Some(redactor) = logredact::for_display(&label) {
+            for group in &mut result.groups {
+                group.title = redactor.redact(&group.title).into_owned();
+                group.exception = group.exception.as_deref().map(|e| redactor.redact(e).into_owned());
+                redactor.redact_record(&mut group.sample);
+            }
+        }
+
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("分析错误日志失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logparse;
+
+    #[test]
+    fn normalizes_variable_parts() {
+        assert_eq!(
+            normalize("Request to https://api.openai.com/v1/chat?x=1 failed after 3 retries"),
+            "Request to <url> failed after <n> retries"
+        );
+        assert_eq!(normalize("session 123e4567-e89b-12d3-a456-426614174000 expired"), "session <uuid> expired");
+        assert_eq!(normalize("bad pointer 0xdeadBEEF"), "bad pointer <hex>");
+        assert_eq!(normalize("message a1b2c3d4e5f6 not found"), "message <hex> not found");
+        assert_eq!(normalize("took 1.5s, 20 tokens"), "took <n>s, <n> tokens");
+        assert_eq!(normalize("  spaced \t out\n text "), "spaced out text");
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize("cannot open /home/user/.nanobot/workspace/a.txt"), "cannot open <path>");
+        assert_eq!(normalize(r"cannot open C:\Users\me\notes.md now"), "cannot open <path> now");
+        // 单段的斜杠（如比例、模型名前缀）不视为路径
+        assert_eq!(normalize("using openai/gpt-4o"), "using openai/gpt-<n>o");
+    }
+
+    #[test]
+    fn keeps_plain_words() {
+        // 纯字母的十六进制串是普通单词
+        assert_eq!(normalize("deadline exceeded for feedface"), "deadline exceeded for feedface");
+        assert_eq!(normalize("Connection refused"), "Connection refused");
+    }
+
+    #[test]
+    fn fingerprint_ignores_variable_parts() {
+        let record = |line: &str| logparse::parse_line(line).unwrap();
+
+        let a = record("2025-01-01 10:00:00.000 | ERROR    | nanobot.agent:run:1 - Timeout after 30s for chat 12345");
+        let b = record("2025-01-02 11:00:00.000 | ERROR    | nanobot.agent:run:9 - Timeout after 60s for chat 67890");
+        let c = record("2025-01-02 11:00:00.000 | ERROR    | nanobot.channels:run:9 - Timeout after 60s for chat 67890");
+
+        let (fa, title, exception) = fingerprint(&a);
+        assert_eq!(title, "Timeout after <n>s for chat <n>");
+        assert_eq!(exception, None);
+        assert_eq!(fa, fingerprint(&b).0);
+        assert_ne!(fa, fingerprint(&c).0);
+
+        let mut d = a.clone();
+        d.traceback = vec!["Traceback (most recent call last):".to_string(), "ValueError: id 42".to_string()];
+        let (fd, _, exception) = fingerprint(&d);
+        assert_eq!(exception.as_deref(), Some("ValueError: id <n>"));
+        assert_ne!(fa, fd);
+    }
+}
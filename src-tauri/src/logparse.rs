@@ -0,0 +1,407 @@
+// 日志解析模块
+// 解析 nanobot 使用的 loguru 默认格式：
+//   2025-01-01 12:00:00.123 | INFO     | nanobot.agent.loop:run:42 - message
+// 多行异常堆栈归入前一条记录，无法解析的行（如 gateway 直接打印到 stdout 的内容）作为 UNKNOWN 记录保留
+// 由 nanoboard 管道写入的行带有 "[stdout] " / "[stderr] " 前缀，解析时去掉并记录来源流
+
+use serde::{Deserialize, Serialize};
+
+/// 日志级别（按严重程度排序）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Unknown,
+    Trace,
+    Debug,
+    Info,
+    Success,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    pub fn parse(name: &str) -> LogLevel {
+        match name.trim().to_uppercase().as_str() {
+            "TRACE" => LogLevel::Trace,
+            "DEBUG" => LogLevel::Debug,
+            "INFO" => LogLevel::Info,
+            "SUCCESS" => LogLevel::Success,
+            "WARNING" | "WARN" => LogLevel::Warning,
+            "ERROR" => LogLevel::Error,
+            "CRITICAL" | "FATAL" => LogLevel::Critical,
+            _ => LogLevel::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Unknown => "UNKNOWN",
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Success => "SUCCESS",
+            LogLevel::Warning => "WARNING",
+            LogLevel::Error => "ERROR",
+            LogLevel::Critical => "CRITICAL",
+        }
+    }
+}
+
+/// 一条日志记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRecord {
+    /// 原始时间戳字符串（本地时间，loguru 默认不带时区）
+    pub timestamp: Option<String>,
+    pub level: LogLevel,
+    pub module: Option<String>,
+    pub function: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+    /// 归入该记录的后续行（异常堆栈等）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traceback: Vec<String>,
+    /// 是否按 loguru 格式解析成功
+    pub parsed: bool,
+    /// 首行原文
+    pub raw: String,
+    /// 首行在日志文件中的字节偏移
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// 所在的日志文件名（查询结果可能来自轮转出的归档）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 输出流（stdout / stderr），仅管道模式写入的日志带有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
+}
+
+impl LogRecord {
+    /// 无法解析的行
+    pub fn unparsed(line: &str) -> LogRecord {
+        let (stream, message) = split_stream_tag(line);
+
+        LogRecord {
+            timestamp: None,
+            level: LogLevel::Unknown,
+            module: None,
+            function: None,
+            line: None,
+            message: message.to_string(),
+            traceback: Vec::new(),
+            parsed: false,
+            raw: line.to_string(),
+            offset: None,
+            source: None,
+            stream: stream.map(|s| s.to_string()),
+        }
+    }
+
+    /// 记录对应的全部原始行
+    pub fn raw_lines(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.raw.as_str()).chain(self.traceback.iter().map(|s| s.as_str()))
+    }
+
+    /// 堆栈中最后一个异常行（如 "ValueError: bad value"）
+    pub fn exception_line(&self) -> Option<&str> {
+        self.traceback
+            .iter()
+            .rev()
+            .map(|s| s.as_str())
+            .find(|line| !line.starts_with(char::is_whitespace) && looks_like_exception(line))
+    }
+}
+
+/// 管道模式下写入日志的流标记
+pub const STREAM_TAGS: [(&str, &str); 2] = [("stdout", "[stdout] "), ("stderr", "[stderr] ")];
+
+/// 拆出行首的流标记，返回 (流名称, 去掉标记后的内容)
+pub fn split_stream_tag(line: &str) -> (Option<&'static str>, &str) {
+    for (stream, tag) in STREAM_TAGS {
+        if let Some(rest) = line.strip_prefix(tag) {
+            return (Some(stream), rest);
+        }
+    }
+    (None, line)
+}
+
+/// 判断时间戳是否形如 "YYYY-MM-DD HH:MM:SS"
+fn looks_like_timestamp(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 19
+        && b[..4].iter().all(|c| c.is_ascii_digit())
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && (b[10] == b' ' || b[10] == b'T')
+        && b[13] == b':'
+}
+
+/// 解析单行 loguru 日志，格式不符时返回 None
+pub fn parse_line(line: &str) -> Option<LogRecord> {
+    let (stream, content) = split_stream_tag(line);
+    let (timestamp, rest) = content.split_once(" | ")?;
+    let timestamp = timestamp.trim();
+    if !looks_like_timestamp(timestamp) {
+        return None;
+    }
+
+    let (level, rest) = rest.split_once(" | ")?;
+    let level_name = level.trim();
+    if level_name.is_empty() || !level_name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    // 位置信息形如 module:function:line，消息本身可能包含 " - "，只在第一处切分
+    let (location, message) = match rest.split_once(" - ") {
+        Some((location, message)) if !location.contains(' ') => (Some(location), message),
+        _ => (None, rest),
+    };
+
+    let (module, function, line_no) = match location {
+        Some(location) => {
+            let mut parts = location.rsplitn(3, ':');
+            let line_no = parts.next().and_then(|s| s.parse::<u32>().ok());
+            let function = parts.next().map(|s| s.to_string());
+            let module = parts.next().map(|s| s.to_string());
+            match (module, function, line_no) {
+                (Some(m), Some(f), Some(l)) => (Some(m), Some(f), Some(l)),
+                _ => (Some(location.to_string()), None, None),
+            }
+        }
+        None => (None, None, None),
+    };
+
+    Some(LogRecord {
+        timestamp: Some(timestamp.to_string()),
+        level: LogLevel::parse(level_name),
+        module,
+        function,
+        line: line_no,
+        message: message.to_string(),
+        traceback: Vec::new(),
+        parsed: true,
+        raw: line.to_string(),
+        offset: None,
+        source: None,
+        stream: stream.map(|s| s.to_string()),
+    })
+}
+
+/// 判断行是否像 Python 异常名（如 "ValueError: ..."、"httpx.ConnectError"）
+fn looks_like_exception(line: &str) -> bool {
+    let head = line.split(':').next().unwrap_or("");
+    let name = head.rsplit('.').next().unwrap_or("");
+
+    !head.is_empty()
+        && !head.contains(' ')
+        && head.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '_')
+        && ["Error", "Exception", "Exit", "Interrupt", "Warning", "Timeout"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// 判断行是否属于前一条记录的延续（异常堆栈、loguru diagnose 输出等）
+fn is_continuation(line: &str) -> bool {
+    line.starts_with(char::is_whitespace)
+        || line.starts_with("Traceback")
+        || line.starts_with("During handling of the above exception")
+        || line.starts_with("The above exception was the direct cause")
+        || line.starts_with("> File")
+        || line.starts_with(['│', '└', '├', '^'])
+        || looks_like_exception(line)
+}
+
+/// 增量解析器：逐行输入，记录完整后输出
+#[derive(Debug, Default)]
+pub struct LogParser {
+    pending: Option<LogRecord>,
+}
+
+impl LogParser {
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    /// 输入一行，返回已经完整的上一条记录
+    pub fn push(&mut self, line: &str) -> Option<LogRecord> {
+        self.push_at(line, None)
+    }
+
+    /// 输入一行及其在文件中的偏移
+    pub fn push_at(&mut self, line: &str, offset: Option<u64>) -> Option<LogRecord> {
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if let Some(mut record) = parse_line(line) {
+            record.offset = offset;
+            return self.pending.replace(record).map(finish_record);
+        }
+
+        let (_, content) = split_stream_tag(line);
+
+        if let Some(pending) = self.pending.as_mut() {
+            // 堆栈中间的空行也归入当前记录
+            let in_traceback = !pending.traceback.is_empty();
+            if is_continuation(content) || (in_traceback && content.trim().is_empty()) {
+                pending.traceback.push(content.to_string());
+                return None;
+            }
+        }
+
+        if content.trim().is_empty() {
+            return None;
+        }
+
+        let mut record = LogRecord::unparsed(line);
+        record.offset = offset;
+        self.pending.replace(record).map(finish_record)
+    }
+
+    /// 查看尚未完成的记录
+    pub fn pending(&self) -> Option<&LogRecord> {
+        self.pending.as_ref()
+    }
+
+    /// 输出尚未完成的记录
+    pub fn flush(&mut self) -> Option<LogRecord> {
+        self.pending.take().map(finish_record)
+    }
+}
+
+/// 去掉堆栈末尾的空行
+fn finish_record(mut record: LogRecord) -> LogRecord {
+    while record.traceback.last().map(|l| l.trim().is_empty()).unwrap_or(false) {
+        record.traceback.pop();
+    }
+    record
+}
+
+/// 将多行日志解析为记录列表
+pub fn parse_lines<I, S>(lines: I) -> Vec<LogRecord>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut parser = LogParser::new();
+    let mut records: Vec<LogRecord> = lines
+        .into_iter()
+        .filter_map(|line| parser.push(line.as_ref()))
+        .collect();
+
+    records.extend(parser.flush());
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_loguru_line() {
+        let record = parse_line("2025-01-01 12:00:00.123 | INFO     | nanobot.agent.loop:run:42 - hello - world").unwrap();
+
+        assert_eq!(record.timestamp.as_deref(), Some("2025-01-01 12:00:00.123"));
+        assert_eq!(record.level, LogLevel::Info);
+        assert_eq!(record.module.as_deref(), Some("nanobot.agent.loop"));
+        assert_eq!(record.function.as_deref(), Some("run"));
+        assert_eq!(record.line, Some(42));
+        // 消息中的 " - " 不参与切分
+        assert_eq!(record.message, "hello - world");
+        assert!(record.parsed);
+        assert_eq!(record.stream, None);
+    }
+
+    #[test]
+    fn parses_unusual_locations_and_levels() {
+        let record = parse_line("2025-01-01T12:00:00 | WARN | no location here").unwrap();
+        assert_eq!(record.level, LogLevel::Warning);
+        assert_eq!(record.module, None);
+        assert_eq!(record.message, "no location here");
+
+        let record = parse_line("2025-01-01 12:00:00.000 | CUSTOM | __main__ - started").unwrap();
+        assert_eq!(record.level, LogLevel::Unknown);
+        assert_eq!(record.module.as_deref(), Some("__main__"));
+        assert_eq!(record.function, None);
+
+        assert!(parse_line("not a log line | INFO | x").is_none());
+        assert!(parse_line("2025-01-01 12:00:00.000 | IN FO | x").is_none());
+        assert!(parse_line("🐈 Starting nanobot gateway on port 18790").is_none());
+    }
+
+    #[test]
+    fn strips_stream_tags() {
+        let record = parse_line("[stderr] 2025-01-01 12:00:00.000 | ERROR    | mod:f:1 - boom").unwrap();
+        assert_eq!(record.stream.as_deref(), Some("stderr"));
+        assert_eq!(record.level, LogLevel::Error);
+        assert_eq!(record.raw, "[stderr] 2025-01-01 12:00:00.000 | ERROR    | mod:f:1 - boom");
+
+        let record = LogRecord::unparsed("[stdout] plain print");
+        assert_eq!(record.stream.as_deref(), Some("stdout"));
+        assert_eq!(record.message, "plain print");
+
+        assert_eq!(split_stream_tag("[stdin] x"), (None, "[stdin] x"));
+    }
+
+    #[test]
+    fn groups_multiline_traceback() {
+        let lines = [
+            "2025-01-01 10:00:00.000 | INFO     | nanobot.agent.loop:run:42 - hello",
+            "2025-01-01 10:00:01.000 | ERROR    | nanobot.channels.telegram:start:10 - boom",
+            "Traceback (most recent call last):",
+            "  File \"x.py\", line 1, in <module>",
+            "",
+            "    raise ValueError('x')",
+            "ValueError: x",
+            "",
+            "🐈 Starting nanobot gateway on port 18790",
+            "2025-01-01 10:00:02.000 | DEBUG    | mod:f:1 - the word ERROR here",
+        ];
+        let records = parse_lines(lines);
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1].traceback.len(), 5);
+        assert_eq!(records[1].exception_line(), Some("ValueError: x"));
+        assert_eq!(records[1].raw_lines().count(), 6);
+        assert!(!records[2].parsed);
+        assert_eq!(records[2].level, LogLevel::Unknown);
+        assert_eq!(records[3].level, LogLevel::Debug);
+    }
+
+    #[test]
+    fn traceback_lines_keep_stream_tags_out() {
+        let lines = [
+            "[stderr] 2025-01-01 10:00:01.000 | ERROR    | mod:f:1 - failed",
+            "[stderr] Traceback (most recent call last):",
+            "[stderr]   File \"x.py\", line 1, in <module>",
+            "[stderr] httpx.ConnectError: refused",
+            "[stdout] 2025-01-01 10:00:02.000 | INFO     | mod:f:2 - next",
+        ];
+        let records = parse_lines(lines);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].traceback[0], "Traceback (most recent call last):");
+        assert_eq!(records[0].exception_line(), Some("httpx.ConnectError: refused"));
+        assert_eq!(records[1].stream.as_deref(), Some("stdout"));
+    }
+
+    #[test]
+    fn incremental_parser_holds_pending_record() {
+        let mut parser = LogParser::new();
+
+        assert!(parser.push_at("2025-01-01 10:00:00.000 | ERROR    | m:f:1 - first", Some(0)).is_none());
+        assert!(parser.push("Traceback (most recent call last):\n").is_none());
+        assert_eq!(parser.pending().unwrap().traceback.len(), 1);
+
+        let first = parser.push_at("2025-01-01 10:00:01.000 | INFO     | m:f:2 - second\r\n", Some(120)).unwrap();
+        assert_eq!(first.message, "first");
+        assert_eq!(first.offset, Some(0));
+
+        // 记录之间的空行被忽略
+        assert!(parser.push("").is_none());
+
+        let second = parser.flush().unwrap();
+        assert_eq!(second.message, "second");
+        assert_eq!(second.offset, Some(120));
+        assert!(parser.flush().is_none());
+    }
+}
@@ -0,0 +1,644 @@
+// 日志查询模块
+// 从文件末尾按块向前读取，边读边解析和过滤，只处理当前页需要的部分，
+// 翻看数百 MB 的日志时不必把整个文件读入内存
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::logfiles::{self, LogFile};
+use crate::logparse::{self, LogLevel, LogParser, LogRecord};
+use crate::logredact;
+
+/// 每次向前读取的块大小
+const BLOCK_SIZE: u64 = 64 * 1024;
+
+/// 单次查询默认最多扫描的字节数，过滤条件很稀疏时分多次返回
+const DEFAULT_MAX_SCAN_BYTES: u64 = 32 * 1024 * 1024;
+
+/// 单页默认和最大记录数
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 5000;
+
+/// 找不到记录首行时最多暂存的行数（非 loguru 格式的文件）
+const MAX_PENDING_LINES: usize = 5000;
+
+/// 从指定位置向前逐行读取
+pub struct BackwardLineReader {
+    file: File,
+    /// 尚未读入的区域为 [0, pos)
+    pos: u64,
+    /// 已读入但还未组成完整行的字节，对应 [pos, pos + carry.len())
+    carry: Vec<u8>,
+    done: bool,
+}
+
+impl BackwardLineReader {
+    /// 从 end 位置向前读取（end 之后的内容忽略）
+    pub fn open(path: &Path, end: Option<u64>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let mut end = end.unwrap_or(size).min(size);
+
+        // 文件末尾的换行不产生空行
+        if end > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::Start(end - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] == b'\n' {
+                end -= 1;
+            }
+        }
+
+        Ok(Self {
+            file,
+            pos: end,
+            carry: Vec::new(),
+            done: end == 0,
+        })
+    }
+
+    /// 尚未返回的内容的起始位置之前的字节数
+    pub fn remaining(&self) -> u64 {
+        self.pos + self.carry.len() as u64
+    }
+
+    /// 返回上一行及其起始偏移（从新到旧）
+    pub fn next_line(&mut self) -> io::Result<Option<(u64, String)>> {
+        loop {
+            if let Some(i) = self.carry.iter().rposition(|&b| b == b'\n') {
+                let offset = self.pos + i as u64 + 1;
+                let line = decode_line(&self.carry[i + 1..]);
+                self.carry.truncate(i);
+                return Ok(Some((offset, line)));
+            }
+
+            if self.pos == 0 {
+                if self.done {
+                    return Ok(None);
+                }
+                self.done = true;
+                let line = decode_line(&self.carry);
+                self.carry.clear();
+                return Ok(Some((0, line)));
+            }
+
+            let start = self.pos.saturating_sub(BLOCK_SIZE);
+            let mut block = vec![0u8; (self.pos - start) as usize];
+            self.file.seek(SeekFrom::Start(start))?;
+            self.file.read_exact(&mut block)?;
+
+            block.extend_from_slice(&self.carry);
+            self.carry = block;
+            self.pos = start;
+        }
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches('\r').to_string()
+}
+
+/// 从指定位置向前逐条读取日志记录
+/// 先收集首行之后的续行，遇到 loguru 首行时再按正向顺序交给 LogParser，分组结果与正向解析一致
+pub struct BackwardRecordReader {
+    lines: BackwardLineReader,
+    /// 已解析但尚未返回的记录（从旧到新）
+    ready: Vec<LogRecord>,
+    /// 首行之后的续行（从新到旧）
+    pending: Vec<(u64, String)>,
+}
+
+impl BackwardRecordReader {
+    pub fn open(path: &Path, end: Option<u64>) -> io::Result<Self> {
+        Ok(Self {
+            lines: BackwardLineReader::open(path, end)?,
+            ready: Vec::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// 尚未读取的字节数
+    pub fn remaining(&self) -> u64 {
+        self.lines.remaining()
+    }
+
+    fn parse_group(&mut self, head: Option<(u64, String)>) {
+        let mut parser = LogParser::new();
+        let mut group = Vec::new();
+
+        for (offset, line) in head.into_iter().chain(self.pending.drain(..).rev()) {
+            group.extend(parser.push_at(&line, Some(offset)));
+        }
+        group.extend(parser.flush());
+
+        self.ready = group;
+    }
+
+    /// 返回上一条记录（从新到旧）
+    pub fn next_record(&mut self) -> io::Result<Option<LogRecord>> {
+        loop {
+            if let Some(record) = self.ready.pop() {
+                return Ok(Some(record));
+            }
+
+            match self.lines.next_line()? {
+                Some((offset, line)) => {
+                    if logparse::parse_line(&line).is_some() {
+                        self.parse_group(Some((offset, line)));
+                    } else {
+                        self.pending.push((offset, line));
+                        if self.pending.len() >= MAX_PENDING_LINES {
+                            self.parse_group(None);
+                        }
+                    }
+                }
+                None if !self.pending.is_empty() => self.parse_group(None),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// 日志过滤条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// 只保留这些级别
+    pub levels: Option<Vec<LogLevel>>,
+    /// 最低级别，无法解析级别的记录会被排除
+    pub min_level: Option<LogLevel>,
+    /// 起止时间，格式与日志一致（YYYY-MM-DD HH:MM:SS），也接受 ISO 8601
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// 模块名前缀，如 nanobot.channels
+    pub module: Option<String>,
+    /// 正则表达式，匹配记录全文
+    pub regex: Option<String>,
+    /// 子串（忽略大小写），匹配记录全文
+    pub contains: Option<String>,
+}
+
+/// 预编译的过滤条件
+pub struct CompiledFilter {
+    levels: Option<Vec<LogLevel>>,
+    min_level: Option<LogLevel>,
+    since: Option<String>,
+    until: Option<String>,
+    module: Option<String>,
+    regex: Option<Regex>,
+    contains: Option<String>,
+}
+
+/// 将 ISO 8601 时间转换为日志中的格式，便于按字符串比较
+fn normalize_time(value: &str) -> String {
+    let value = value.trim().replacen('T', " ", 1);
+    let end = value
+        .char_indices()
+        .skip(19)
+        .find(|(_, c)| matches!(c, 'Z' | '+' | '-'))
+        .map(|(i, _)| i)
+        .unwrap_or(value.len());
+    value[..end].to_string()
+}
+
+impl LogFilter {
+    pub fn compile(&self) -> Result<CompiledFilter, String> {
+        let regex = match self.regex.as_deref().filter(|r| !r.is_empty()) {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("无效的正则表达式: {}", e))?),
+            None => None,
+        };
+
+        let non_empty = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+
+        Ok(CompiledFilter {
+            levels: self.levels.clone().filter(|l| !l.is_empty()),
+            min_level: self.min_level,
+            since: non_empty(&self.since).map(|s| normalize_time(&s)),
+            until: non_empty(&self.until).map(|s| normalize_time(&s)),
+            module: non_empty(&self.module),
+            regex,
+            contains: non_empty(&self.contains).map(|s| s.to_lowercase()),
+        })
+    }
+}
+
+impl CompiledFilter {
+    /// 是否有时间条件
+    pub fn has_time_range(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// 记录是否早于起始时间（向前读取时可以就此停止）
+    pub fn is_before_range(&self, record: &LogRecord) -> bool {
+        match (&self.since, &record.timestamp) {
+            (Some(since), Some(ts)) => ts.as_str() < since.as_str(),
+            _ => false,
+        }
+    }
+
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(levels) = &self.levels {
+            if !levels.contains(&record.level) {
+                return false;
+            }
+        }
+
+        if let Some(min) = self.min_level {
+            if record.level == LogLevel::Unknown || record.level < min {
+                return false;
+            }
+        }
+
+        if self.has_time_range() {
+            let Some(ts) = record.timestamp.as_deref() else {
+                return false;
+            };
+            if self.since.as_deref().map(|s| ts < s).unwrap_or(false) {
+                return false;
+            }
+            // 截止时间按其自身精度包含（until 为 10:00:09 时包含 10:00:09.500）
+            if self.until.as_deref().map(|u| ts.get(..u.len()).unwrap_or(ts) > u).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        if let Some(module) = &self.module {
+            if !record.module.as_deref().map(|m| m.starts_with(module.as_str())).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        if self.regex.is_none() && self.contains.is_none() {
+            return true;
+        }
+
+        let text = record.raw_lines().collect::<Vec<_>>().join("\n");
+
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(&text) {
+                return false;
+            }
+        }
+
+        if let Some(needle) = &self.contains {
+            if !text.to_lowercase().contains(needle.as_str()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// 查询参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogQuery {
+    #[serde(flatten)]
+    pub filter: LogFilter,
+    /// 上一页返回的 nextCursor，为空时从文件末尾开始
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    pub max_scan_bytes: Option<u64>,
+}
+
+/// 查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogQueryResult {
+    /// 按时间正序排列
+    pub records: Vec<LogRecord>,
+    /// 用于读取更早记录的游标，没有更多内容时为空
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    pub scanned_bytes: u64,
+}
+
+/// 游标格式为 "<文件指纹>:<偏移>"，文件被轮转改名后仍能定位
+fn format_cursor(fingerprint: &str, offset: u64) -> String {
+    format!("{}:{}", fingerprint, offset)
+}
+
+/// 解析游标，返回 (文件指纹, 偏移)；只有偏移时表示当前日志
+fn parse_cursor(cursor: &str) -> Result<(Option<String>, u64), String> {
+    let invalid = || format!("无效的游标: {}", cursor);
+
+    match cursor.trim().rsplit_once(':') {
+        Some((fingerprint, offset)) => Ok((
+            Some(fingerprint.to_string()),
+            offset.parse::<u64>().map_err(|_| invalid())?,
+        )),
+        None => Ok((None, cursor.trim().parse::<u64>().map_err(|_| invalid())?)),
+    }
+}
+
+/// 在当前日志及其归档上执行查询，files 按从新到旧排列
+pub fn query_files(files: &[LogFile], query: &LogQuery) -> Result<LogQueryResult, String> {
+    let filter = query.filter.compile()?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let max_scan = query.max_scan_bytes.unwrap_or(DEFAULT_MAX_SCAN_BYTES).max(BLOCK_SIZE);
+
+    let mut fingerprints: Vec<Option<String>> = vec![None; files.len()];
+    let mut fingerprint_of = |index: usize| -> Result<String, String> {
+        if fingerprints[index].is_none() {
+            let fp = logfiles::fingerprint(&files[index])
+                .map_err(|e| format!("读取日志文件 {} 失败: {}", files[index].name, e))?;
+            fingerprints[index] = Some(fp);
+        }
+        Ok(fingerprints[index].clone().unwrap_or_default())
+    };
+
+    // 定位游标所在的文件
+    let (start_index, mut end) = match query.cursor.as_deref().map(parse_cursor).transpose()? {
+        None => (0, None),
+        Some((None, offset)) => (0, Some(offset)),
+        Some((Some(fp), offset)) => {
+            let mut found = None;
+            for index in 0..files.len() {
+                if fingerprint_of(index)? == fp {
+                    found = Some(index);
+                    break;
+                }
+            }
+            (found.ok_or_else(|| "游标对应的日志文件已不存在".to_string())?, Some(offset))
+        }
+    };
+
+    let mut records = Vec::new();
+    let mut scanned = 0u64;
+    // 最后一条已检查记录的位置，作为下一页的游标
+    let mut last: Option<(usize, u64)> = None;
+    let mut reached_start = true;
+
+    'files: for index in start_index..files.len() {
+        let file = &files[index];
+        let path = logfiles::readable_path(file)?;
+
+        let mut reader = BackwardRecordReader::open(&path, end.take())
+            .map_err(|e| format!("打开日志文件 {} 失败: {}", file.name, e))?;
+        let start = reader.remaining();
+
+        loop {
+            if records.len() >= limit || scanned + (start - reader.remaining()) >= max_scan {
+                scanned += start - reader.remaining();
+                reached_start = false;
+                break 'files;
+            }
+
+            let record = reader
+                .next_record()
+                .map_err(|e| format!("读取日志文件 {} 失败: {}", file.name, e))?;
+
+            let Some(mut record) = record else {
+                break;
+            };
+
+            // 日志按时间顺序写入，早于起始时间即可停止（更早的归档也无需再读）
+            if filter.is_before_range(&record) {
+                scanned += start - reader.remaining();
+                break 'files;
+            }
+
+            last = record.offset.map(|o| (index, o));
+
+            if filter.matches(&record) {
+                record.source = Some(file.name.clone());
+                records.push(record);
+            }
+        }
+
+        scanned += start;
+
+        // 当前文件读完后从下一个归档的末尾继续
+        if index + 1 < files.len() {
+            last = Some((index + 1, u64::MAX));
+        }
+    }
+
+    records.reverse();
+
+    let next_cursor = match last {
+        // 停在最后一个文件的开头时已没有更早的内容
+        Some((index, offset)) if !reached_start && (offset != 0 || index + 1 != files.len()) => {
+            Some(format_cursor(&fingerprint_of(index)?, offset))
+        }
+        _ => None,
+    };
+
+    Ok(LogQueryResult {
+        records,
+        has_more: next_cursor.is_some(),
+        next_cursor,
+        scanned_bytes: scanned,
+    })
+}
+
+/// 查询 nanobot 日志（包括轮转出的归档）
+/// 从最新的日志末尾（或游标位置）向前读取，返回满足条件的最近 limit 条记录
+#[tauri::command]
+pub async fn query_logs(window: tauri::Window, query: LogQuery) -> Result<LogQueryResult, String> {
+    let label = window.label().to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let files = logfiles::discover_log_files()?;
+        let mut result = query_files(&files, &query)?;
+        logredact::redact_for_display(&label, &mut result.records);
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("查询日志失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 每个测试使用独立的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nanoboard-logquery-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn log_file(path: &Path, current: bool) -> LogFile {
+        LogFile {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            path: path.to_path_buf(),
+            size: std::fs::metadata(path).unwrap().len(),
+            modified: 0,
+            compressed: false,
+            current,
+        }
+    }
+
+    fn record_lines(from: usize, to: usize) -> String {
+        (from..=to)
+            .map(|i| format!("2025-01-01 10:00:{:02}.000 | INFO     | m:f:1 - record {}\n", i, i))
+            .collect()
+    }
+
+    fn read_all_lines(reader: &mut BackwardLineReader) -> Vec<(u64, String)> {
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line().unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[test]
+    fn backward_lines_match_forward_offsets() {
+        let dir = temp_dir("lines");
+        let path = dir.join("a.log");
+
+        // 跨越多个块，并包含 CRLF 和空行
+        let mut content = String::new();
+        for i in 0..3000 {
+            content.push_str(&format!("line {} {}\r\n", i, "x".repeat(i % 50)));
+            if i % 700 == 0 {
+                content.push('\n');
+            }
+        }
+        std::fs::write(&path, &content).unwrap();
+
+        let mut expected = Vec::new();
+        let mut offset = 0u64;
+        for line in content.split_inclusive('\n') {
+            expected.push((offset, line.trim_end_matches(['\r', '\n']).to_string()));
+            offset += line.len() as u64;
+        }
+        expected.reverse();
+
+        let mut reader = BackwardLineReader::open(&path, None).unwrap();
+        assert_eq!(read_all_lines(&mut reader), expected);
+        assert_eq!(reader.remaining(), 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn backward_lines_respect_end_and_missing_newline() {
+        let dir = temp_dir("end");
+        let path = dir.join("a.log");
+        std::fs::write(&path, "first\nsecond\nthird").unwrap();
+
+        let mut reader = BackwardLineReader::open(&path, None).unwrap();
+        assert_eq!(
+            read_all_lines(&mut reader),
+            vec![(13, "third".to_string()), (6, "second".to_string()), (0, "first".to_string())]
+        );
+
+        // 从 "second" 的起始位置向前读
+        let mut reader = BackwardLineReader::open(&path, Some(6)).unwrap();
+        assert_eq!(read_all_lines(&mut reader), vec![(0, "first".to_string())]);
+
+        std::fs::write(&path, "").unwrap();
+        let mut reader = BackwardLineReader::open(&path, None).unwrap();
+        assert!(reader.next_line().unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn backward_records_group_like_forward_parse() {
+        let dir = temp_dir("records");
+        let path = dir.join("a.log");
+        let content = "\
+orphan continuation before first record
+2025-01-01 10:00:00.000 | INFO     | m:f:1 - one
+2025-01-01 10:00:01.000 | ERROR    | m:f:2 - two
+Traceback (most recent call last):
+  File \"x.py\", line 1, in <module>
+ValueError: bad
+2025-01-01 10:00:02.000 | INFO     | m:f:3 - three
+";
+        std::fs::write(&path, content).unwrap();
+
+        let mut reader = BackwardRecordReader::open(&path, None).unwrap();
+        let mut backward = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            backward.push(record);
+        }
+        backward.reverse();
+
+        let forward = logparse::parse_lines(content.lines());
+        let strip = |records: &[LogRecord]| -> Vec<(String, Vec<String>)> {
+            records.iter().map(|r| (r.message.clone(), r.traceback.clone())).collect()
+        };
+        assert_eq!(strip(&backward), strip(&forward));
+        assert_eq!(backward[2].offset, Some(content.find("2025-01-01 10:00:01").unwrap() as u64));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    fn messages(result: &LogQueryResult) -> Vec<String> {
+        result.records.iter().map(|r| r.message.clone()).collect()
+    }
+
+    #[test]
+    fn cursor_survives_rotation() {
+        let dir = temp_dir("rotation");
+        let current = dir.join("nanobot.log");
+        let archive = dir.join("nanobot.log.1");
+        std::fs::write(&current, record_lines(1, 10)).unwrap();
+
+        let query = |files: &[LogFile], cursor: Option<String>| {
+            query_files(files, &LogQuery {
+                cursor,
+                limit: Some(4),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        let first = query(&[log_file(&current, true)], None);
+        assert_eq!(messages(&first), vec!["record 7", "record 8", "record 9", "record 10"]);
+        assert!(first.has_more);
+
+        // 轮转：当前日志改名为归档，新日志写入更多记录
+        std::fs::rename(&current, &archive).unwrap();
+        std::fs::write(&current, record_lines(11, 12)).unwrap();
+        let files = [log_file(&current, true), log_file(&archive, false)];
+
+        let second = query(&files, first.next_cursor.clone());
+        assert_eq!(messages(&second), vec!["record 3", "record 4", "record 5", "record 6"]);
+        assert!(second.records.iter().all(|r| r.source.as_deref() == Some("nanobot.log.1")));
+
+        let third = query(&files, second.next_cursor.clone());
+        assert_eq!(messages(&third), vec!["record 1", "record 2"]);
+        assert!(!third.has_more);
+        assert_eq!(third.next_cursor, None);
+
+        // 从头查询时先读当前日志，再接着读归档
+        let latest = query(&files, None);
+        assert_eq!(messages(&latest), vec!["record 9", "record 10", "record 11", "record 12"]);
+        let next = query(&files, latest.next_cursor.clone());
+        assert_eq!(messages(&next), vec!["record 5", "record 6", "record 7", "record 8"]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rejects_invalid_or_stale_cursor() {
+        let dir = temp_dir("cursor");
+        let current = dir.join("nanobot.log");
+        std::fs::write(&current, record_lines(1, 3)).unwrap();
+        let files = [log_file(&current, true)];
+
+        assert_eq!(parse_cursor("abc:42").unwrap(), (Some("abc".to_string()), 42));
+        assert_eq!(parse_cursor("42").unwrap(), (None, 42));
+        assert!(parse_cursor("abc:x").is_err());
+
+        let query = LogQuery {
+            cursor: Some("0000000000000000:10".to_string()),
+            ..Default::default()
+        };
+        assert!(query_files(&files, &query).unwrap_err().contains("不存在"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,195 @@
+// 日志脱敏模块
+// gateway 日志中可能出现 API Key、Bot Token、Authorization 头等敏感内容，
+// 按常见密钥格式和当前配置中的密钥明文进行替换。
+// 发送到前端的日志默认脱敏，可在本次运行中临时为某个窗口显示原文（不保存，窗口关闭或重启后恢复脱敏）
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::config;
+use crate::logparse::LogRecord;
+use crate::secrets::{self, REDACTED};
+
+/// 配置中短于此长度的值不作为字面量替换，避免误伤普通文本
+const MIN_LITERAL_LEN: usize = 6;
+
+/// 常见密钥格式，替换内容中的 $1、$2 保留键名等上下文
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| {
+        [
+            // Authorization: Bearer xxx / Basic xxx
+            (r"(?i)\b(bearer|basic)\s+[A-Za-z0-9._~+/=\-]{8,}", "$1 [REDACTED]"),
+            // OpenAI、Anthropic、OpenRouter 等 sk- 开头的 Key
+            (r"\bsk-[A-Za-z0-9_\-]{16,}", REDACTED),
+            // Telegram Bot Token
+            (r"\b\d{6,12}:[A-Za-z0-9_\-]{30,}", REDACTED),
+            // Slack Token
+            (r"\bx(?:ox[abposr]|app)-[A-Za-z0-9\-]{10,}", REDACTED),
+            // GitHub Token
+            (r"\bgh[pousr]_[A-Za-z0-9]{30,}", REDACTED),
+            // Google API Key
+            (r"\bAIza[0-9A-Za-z_\-]{35}", REDACTED),
+            // URL 参数中的 key / token
+            (r"(?i)([?&](?:key|api_key|apikey|token|access_token)=)[^&\s]+", "${1}[REDACTED]"),
+            // api_key=xxx、"token": "xxx" 等键值对
+            (
+                r#"(?i)\b((?:api[_-]?key|apikey|access[_-]?token|refresh[_-]?token|bot[_-]?token|app[_-]?secret|client[_-]?secret|secret|password|token)["']?\s*[:=]\s*["']?)[^\s"',;&}]{6,}"#,
+                "${1}[REDACTED]",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(pattern, replacement)| Regex::new(pattern).ok().map(|r| (r, replacement)))
+        .collect()
+    })
+}
+
+/// 日志脱敏器
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// 配置中的密钥明文，按长度从长到短排列，避免较短的值先替换掉较长值的一部分
+    literals: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(mut literals: Vec<String>) -> Self {
+        literals.retain(|v| v.chars().count() >= MIN_LITERAL_LEN && v != REDACTED);
+        literals.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        literals.dedup();
+        Self { literals }
+    }
+
+    /// 使用当前配置中的密钥构建（配置读取失败时只按格式替换）
+    pub fn from_config() -> Self {
+        let values = config::read_config_file()
+            .and_then(|c| secrets::decrypt_secrets(&c).map_err(|e| e.to_string()))
+            .map(|c| secrets::secret_values(&c))
+            .unwrap_or_default();
+
+        Self::new(values)
+    }
+
+    /// 替换文本中的密钥
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+
+        for literal in &self.literals {
+            if text.contains(literal.as_str()) {
+                text = Cow::Owned(text.replace(literal.as_str(), REDACTED));
+            }
+        }
+
+        for (regex, replacement) in patterns() {
+            let replaced = match regex.replace_all(&text, *replacement) {
+                Cow::Owned(replaced) => Some(replaced),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(replaced) = replaced {
+                text = Cow::Owned(replaced);
+            }
+        }
+
+        text
+    }
+
+    /// 替换记录中的消息、原文和堆栈
+    pub fn redact_record(&self, record: &mut LogRecord) {
+        for field in std::iter::once(&mut record.message)
+            .chain(std::iter::once(&mut record.raw))
+            .chain(record.traceback.iter_mut())
+        {
+            let redacted = match self.redact(field) {
+                Cow::Owned(redacted) => Some(redacted),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(redacted) = redacted {
+                *field = redacted;
+            }
+        }
+    }
+}
+
+/// 本次运行中显示日志原文的窗口
+fn revealed_windows() -> &'static Mutex<HashSet<String>> {
+    static REVEALED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    REVEALED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// 窗口是否显示日志原文
+pub fn is_revealed(label: &str) -> bool {
+    revealed_windows().lock().unwrap().contains(label)
+}
+
+/// 窗口关闭后恢复脱敏，同名的新窗口不会继承
+pub fn forget_window(label: &str) {
+    revealed_windows().lock().unwrap().remove(label);
+}
+
+/// 按配置文件修改时间缓存的脱敏器
+static DISPLAY_REDACTOR: Mutex<Option<(Option<SystemTime>, Arc<Redactor>)>> = Mutex::new(None);
+
+/// 当前配置对应的脱敏器，配置文件变化后重新读取密钥
+pub fn current() -> Arc<Redactor> {
+    let modified = config::get_config_path_internal()
+        .ok()
+        .and_then(|p| std::fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok());
+
+    let mut cache = DISPLAY_REDACTOR.lock().unwrap();
+    match cache.as_ref() {
+        Some((cached, redactor)) if *cached == modified => redactor.clone(),
+        _ => {
+            let redactor = Arc::new(Redactor::from_config());
+            *cache = Some((modified, redactor.clone()));
+            redactor
+        }
+    }
+}
+
+/// 发往指定窗口时使用的脱敏器，该窗口显示原文时为 None
+pub fn for_display(label: &str) -> Option<Arc<Redactor>> {
+    if is_revealed(label) {
+        None
+    } else {
+        Some(current())
+    }
+}
+
+/// 对即将发送到指定窗口的记录脱敏
+pub fn redact_for_display(label: &str, records: &mut [LogRecord]) {
+    if let Some(redactor) = for_display(label) {
+        for record in records {
+            redactor.redact_record(record);
+        }
+    }
+}
+
+/// 对即将发送到指定窗口的文本脱敏
+pub fn redact_text_for_display(label: &str, text: &str) -> String {
+    match for_display(label) {
+        Some(redactor) => redactor.redact(text).into_owned(),
+        None => text.to_string(),
+    }
+}
+
+/// 获取当前窗口是否显示日志原文
+#[tauri::command]
+pub async fn get_log_secrets_revealed(window: tauri::Window) -> Result<bool, String> {
+    Ok(is_revealed(window.label()))
+}
+
+/// 设置当前窗口在本次运行中是否显示日志原文（不保存，其他窗口和推送事件不受影响）
+#[tauri::command]
+pub async fn set_log_secrets_revealed(window: tauri::Window, revealed: bool) -> Result<bool, String> {
+    let mut windows = revealed_windows().lock().unwrap();
+    if revealed {
+        windows.insert(window.label().to_string());
+    } else {
+        windows.remove(window.label());
+    }
+    Ok(revealed)
+}
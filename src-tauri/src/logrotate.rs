@@ -0,0 +1,364 @@
+// 日志轮转模块
+// 默认情况下 gateway 的 stdout/stderr 直接重定向到 nanobot.log，文件会无限增长。
+// 开启管道模式后，输出经由 nanoboard 持有的管道逐行写入日志：
+// 按大小或时间轮转（nanobot.log -> nanobot.log.1 -> ...），限制保留数量和天数，可选 gzip 压缩旧文件，
+// 每行带上 "[stdout] " / "[stderr] " 标记。
+// 注意：管道模式下 gateway 的输出依赖 nanoboard 转发，关闭 nanoboard 后 gateway 将无法继续写日志。
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::fsutil;
+use crate::logger;
+use crate::logparse::STREAM_TAGS;
+
+/// 轮转触发方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationPolicy {
+    /// 超过 maxBytes 时轮转
+    Size,
+    /// 每小时轮转（同时受 maxBytes 限制）
+    Hourly,
+    /// 每天轮转（同时受 maxBytes 限制）
+    Daily,
+}
+
+/// 日志轮转设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogRotationSettings {
+    /// 是否通过管道接管 gateway 输出（下次启动 gateway 时生效）
+    pub enabled: bool,
+    pub policy: RotationPolicy,
+    /// 单个日志文件的最大字节数
+    pub max_bytes: u64,
+    /// 最多保留的归档数量
+    pub max_files: usize,
+    /// 归档最多保留的天数，0 表示不按时间清理
+    pub max_age_days: u32,
+    /// 是否 gzip 压缩归档
+    pub compress: bool,
+    /// 是否在每行前标记来源流
+    pub tag_streams: bool,
+}
+
+impl Default for LogRotationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            policy: RotationPolicy::Size,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            max_age_days: 0,
+            compress: true,
+            tag_streams: true,
+        }
+    }
+}
+
+/// 单个日志文件大小下限，避免过于频繁的轮转
+const MIN_MAX_BYTES: u64 = 64 * 1024;
+
+/// 归档数量上限
+const MAX_FILES_LIMIT: usize = 100;
+
+impl LogRotationSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_bytes < MIN_MAX_BYTES {
+            return Err(format!("单个日志文件大小不能小于 {} KB", MIN_MAX_BYTES / 1024));
+        }
+        if self.max_files == 0 || self.max_files > MAX_FILES_LIMIT {
+            return Err(format!("保留数量必须在 1 到 {} 之间", MAX_FILES_LIMIT));
+        }
+        Ok(())
+    }
+
+    /// 当前时间所属的轮转周期
+    fn period(&self, time: chrono::DateTime<chrono::Local>) -> Option<String> {
+        match self.policy {
+            RotationPolicy::Size => None,
+            RotationPolicy::Hourly => Some(time.format("%Y-%m-%d %H").to_string()),
+            RotationPolicy::Daily => Some(time.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+/// 获取设置文件路径
+fn get_settings_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".nanobot").join("log_rotation.json")
+}
+
+/// 加载设置，文件不存在或无法解析时使用默认值
+pub fn load_settings() -> LogRotationSettings {
+    fs::read_to_string(get_settings_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存设置
+fn save_settings(settings: &LogRotationSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("序列化日志轮转设置失败: {}", e))?;
+
+    fsutil::atomic_write(&get_settings_path(), content)
+        .map_err(|e| format!("保存日志轮转设置失败: {}", e))
+}
+
+/// 归档文件路径：nanobot.log.N 或 nanobot.log.N.gz
+fn archive_path(path: &Path, index: usize, compressed: bool) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let suffix = if compressed { ".gz" } else { "" };
+    path.with_file_name(format!("{}.{}{}", name, index, suffix))
+}
+
+/// 按设置写入并轮转的日志文件
+pub struct RotatingLog {
+    path: PathBuf,
+    settings: LogRotationSettings,
+    file: File,
+    size: u64,
+    /// 当前文件所属的时间周期（按时间轮转时使用）
+    period: Option<String>,
+    /// 正在后台压缩上一个归档的线程
+    compressing: Option<std::thread::JoinHandle<()>>,
+}
+
+impl RotatingLog {
+    pub fn open(path: &Path, settings: LogRotationSettings) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+
+        // 已有内容的文件按最后修改时间确定所属周期，跨周期后写入第一行时即轮转
+        let period = if metadata.len() > 0 {
+            metadata
+                .modified()
+                .ok()
+                .map(chrono::DateTime::<chrono::Local>::from)
+                .and_then(|t| settings.period(t))
+        } else {
+            settings.period(chrono::Local::now())
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            settings,
+            file,
+            period,
+            compressing: None,
+        })
+    }
+
+    /// 写入一行（不含换行符）
+    pub fn write_line(&mut self, stream: &str, line: &[u8]) -> io::Result<()> {
+        let tag = STREAM_TAGS
+            .iter()
+            .find(|(name, _)| self.settings.tag_streams && *name == stream)
+            .map(|(_, tag)| *tag)
+            .unwrap_or("");
+
+        let mut buf = Vec::with_capacity(tag.len() + line.len() + 1);
+        buf.extend_from_slice(tag.as_bytes());
+        buf.extend_from_slice(line);
+        buf.push(b'\n');
+
+        let period = self.settings.period(chrono::Local::now());
+        let too_large = self.size > 0 && self.size + buf.len() as u64 > self.settings.max_bytes;
+
+        if too_large || (self.size > 0 && period != self.period) {
+            self.rotate()?;
+        }
+        self.period = period;
+
+        // 每行直接写入文件，便于实时日志流及时读到
+        self.file.write_all(&buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    /// 轮转：依次后移归档，当前文件改名为 .1，再创建新文件
+    /// 压缩在后台线程中进行，避免持有锁期间阻塞 stdout/stderr 的转发
+    pub fn rotate(&mut self) -> io::Result<()> {
+        let max_files = self.settings.max_files;
+
+        // 上一次的压缩还没完成时等待，避免后移归档时与压缩线程冲突
+        if let Some(task) = self.compressing.take() {
+            let _ = task.join();
+        }
+
+        for compressed in [false, true] {
+            let oldest = archive_path(&self.path, max_files, compressed);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+        }
+
+        for index in (1..max_files).rev() {
+            for compressed in [false, true] {
+                let from = archive_path(&self.path, index, compressed);
+                if from.exists() {
+                    fs::rename(&from, archive_path(&self.path, index + 1, compressed))?;
+                }
+            }
+        }
+
+        let archived = archive_path(&self.path, 1, false);
+        fs::rename(&self.path, &archived)?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        self.apply_retention();
+
+        if self.settings.compress {
+            self.compressing = Some(std::thread::spawn(move || {
+                if let Err(e) = compress_file(&archived) {
+                    log::warn!("压缩日志归档 {:?} 失败: {}", archived, e);
+                }
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// 本轮转器创建的归档：<文件名>.N 或 <文件名>.N.gz，返回 (序号, 路径)
+    fn own_archives(&self) -> Vec<(usize, PathBuf)> {
+        let name = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let Some(dir) = self.path.parent() else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let rest = file_name.strip_prefix(&name)?.strip_prefix('.')?;
+                let index = rest.strip_suffix(".gz").unwrap_or(rest).parse::<usize>().ok()?;
+                Some((index, entry.path()))
+            })
+            .collect()
+    }
+
+    /// 删除超出数量或天数的归档（只处理本轮转器创建的 .N / .N.gz，不动 loguru 等其他来源的文件）
+    fn apply_retention(&self) {
+        let cutoff = match self.settings.max_age_days {
+            0 => None,
+            days => chrono::Local::now()
+                .checked_sub_signed(chrono::Duration::days(days as i64))
+                .map(std::time::SystemTime::from),
+        };
+
+        for (index, path) in self.own_archives() {
+            let expired = cutoff
+                .zip(fs::metadata(&path).and_then(|m| m.modified()).ok())
+                .map(|(cutoff, modified)| modified < cutoff)
+                .unwrap_or(false);
+
+            if index > self.settings.max_files || expired {
+                if let Err(e) = fs::remove_file(&path) {
+                    log::warn!("删除日志归档 {:?} 失败: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// gzip 压缩文件为 <文件名>.gz 并删除原文件
+fn compress_file(path: &Path) -> io::Result<()> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let target = path.with_file_name(format!("{}.gz", name));
+    let tmp = path.with_file_name(format!(".{}.gz.tmp", name));
+
+    let result = (|| -> io::Result<()> {
+        let mut input = File::open(path)?;
+        let mut encoder = GzEncoder::new(File::create(&tmp)?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(&tmp, &target)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    // 保留与原文件相同的修改时间，归档按时间排序和清理时才准确
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        if let Ok(file) = File::options().write(true).open(&target) {
+            let _ = file.set_modified(modified);
+        }
+    }
+
+    fs::remove_file(path)
+}
+
+/// 逐行读取子进程输出并写入轮转日志，直到管道关闭
+pub fn pump<R: Read + Send + 'static>(
+    reader: R,
+    stream: &'static str,
+    log: Arc<Mutex<RotatingLog>>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+                    if let Err(e) = log.lock().unwrap().write_line(stream, line) {
+                        log::error!("写入 gateway {} 输出失败: {}", stream, e);
+                    }
+                }
+                Err(e) => {
+                    log::error!("读取 gateway {} 输出失败: {}", stream, e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// 打开当前日志文件用于管道写入
+pub fn open_gateway_log(settings: LogRotationSettings) -> Result<Arc<Mutex<RotatingLog>>, String> {
+    let path = logger::get_log_path().map_err(|e| e.to_string())?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("创建日志目录失败: {}", e))?;
+    }
+
+    let log = RotatingLog::open(&path, settings)
+        .map_err(|e| format!("无法打开日志文件: {}", e))?;
+
+    Ok(Arc::new(Mutex::new(log)))
+}
+
+/// 获取日志轮转设置
+#[tauri::command]
+pub async fn get_log_rotation_settings() -> Result<LogRotationSettings, String> {
+    Ok(load_settings())
+}
+
+/// 保存日志轮转设置（管道模式的开关在下次启动 gateway 时生效）
+#[tauri::command]
+pub async fn set_log_rotation_settings(settings: LogRotationSettings) -> Result<LogRotationSettings, String> {
+    settings.validate()?;
+    save_settings(&settings)?;
+    Ok(settings)
+}
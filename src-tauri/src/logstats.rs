@@ -0,0 +1,246 @@
+// 日志统计模块
+// 记住已处理到的字节位置，每次只解析新增内容并累加各级别计数，
+// 同时保留最近 24 小时按分钟划分的级别直方图
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::logfiles;
+use crate::logger;
+use crate::logparse::{LogLevel, LogParser, LogRecord};
+
+/// 直方图保留的分钟数
+const HISTOGRAM_MINUTES: i64 = 24 * 60;
+
+/// 各级别计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelCounts {
+    pub total: usize,
+    pub debug: usize,
+    pub info: usize,
+    pub warn: usize,
+    pub error: usize,
+}
+
+impl LevelCounts {
+    fn add(&mut self, level: LogLevel) {
+        self.total += 1;
+        match level {
+            LogLevel::Trace | LogLevel::Debug => self.debug += 1,
+            LogLevel::Info | LogLevel::Success => self.info += 1,
+            LogLevel::Warning => self.warn += 1,
+            LogLevel::Error | LogLevel::Critical => self.error += 1,
+            LogLevel::Unknown => {}
+        }
+    }
+}
+
+/// 直方图中的一分钟
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// YYYY-MM-DD HH:MM（日志本地时间）
+    pub minute: String,
+    #[serde(flatten)]
+    pub counts: LevelCounts,
+}
+
+/// 增量统计状态
+struct LogStats {
+    path: PathBuf,
+    /// 已处理到的位置（总是在完整行之后）
+    offset: u64,
+    /// 文件开头的字节，用于识别轮转后的新文件
+    head: Vec<u8>,
+    counts: LevelCounts,
+    histogram: BTreeMap<String, LevelCounts>,
+    /// 最后一条记录可能还有后续堆栈行，暂不计入
+    parser: LogParser,
+}
+
+impl LogStats {
+    const fn empty() -> Self {
+        Self {
+            path: PathBuf::new(),
+            offset: 0,
+            head: Vec::new(),
+            counts: LevelCounts { total: 0, debug: 0, info: 0, warn: 0, error: 0 },
+            histogram: BTreeMap::new(),
+            parser: LogParser::new(),
+        }
+    }
+
+    /// 文件被截断或替换时从头统计，直方图按时间保留
+    fn reset(&mut self, path: &Path) {
+        self.path = path.to_path_buf();
+        self.offset = 0;
+        self.head.clear();
+        self.counts = LevelCounts::default();
+        self.parser = LogParser::new();
+    }
+
+    fn record(&mut self, record: &LogRecord) {
+        self.counts.add(record.level);
+
+        if let Some(minute) = record.timestamp.as_deref().and_then(minute_key) {
+            self.histogram.entry(minute).or_default().add(record.level);
+        }
+    }
+
+    /// 从 self.offset 开始读取文件中的完整行；complete 为 true 时文件不会再写入，最后一行没有换行也计入
+    fn read_lines(&mut self, path: &Path, complete: bool) -> std::io::Result<()> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+
+            // 最后一行可能还没写完，留到下次处理
+            if n == 0 || (!complete && buf.last() != Some(&b'\n')) {
+                break;
+            }
+
+            self.offset += n as u64;
+            let line = String::from_utf8_lossy(&buf);
+            if let Some(record) = self.parser.push(&line) {
+                self.record(&record);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 当前文件被轮转后，先读完旧文件（已改名为归档）中尚未处理的内容，避免丢失轮转前最后写入的记录
+    fn finish_rotated(&mut self) {
+        if self.offset > 0 {
+            let archive = logfiles::find_archive_by_head(&self.head)
+                .and_then(|archive| logfiles::readable_path(&archive).ok());
+
+            if let Some(archive) = archive {
+                if let Err(e) = self.read_lines(&archive, true) {
+                    log::warn!("读取轮转后的日志归档失败: {}", e);
+                }
+            }
+        }
+
+        if let Some(record) = self.parser.flush() {
+            self.record(&record);
+        }
+    }
+
+    /// 处理新增内容
+    fn update(&mut self, path: &Path) -> std::io::Result<()> {
+        if self.path != path {
+            self.reset(path);
+        }
+
+        if !path.exists() {
+            self.reset(path);
+            return Ok(());
+        }
+
+        let size = std::fs::metadata(path)?.len();
+        let head = logfiles::read_head(path, false)?;
+
+        // 文件变小说明被截断；开头内容不同说明已被轮转替换
+        if size < self.offset || !logfiles::same_head(&head, &self.head) {
+            self.finish_rotated();
+            self.reset(path);
+        }
+        if head.len() > self.head.len() {
+            self.head = head;
+        }
+
+        if size == self.offset {
+            return Ok(());
+        }
+
+        self.read_lines(path, false)?;
+        self.prune();
+        Ok(())
+    }
+
+    /// 删除 24 小时之前的直方图数据
+    fn prune(&mut self) {
+        let cutoff = (chrono::Local::now() - chrono::Duration::minutes(HISTOGRAM_MINUTES))
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+        self.histogram = self.histogram.split_off(&cutoff);
+    }
+
+    /// 当前计数（包含尚未完成的最后一条记录）
+    fn snapshot(&self) -> LevelCounts {
+        let mut counts = self.counts;
+        if let Some(pending) = self.parser.pending() {
+            counts.add(pending.level);
+        }
+        counts
+    }
+
+    fn histogram(&self, minutes: i64) -> Vec<HistogramBucket> {
+        let cutoff = (chrono::Local::now() - chrono::Duration::minutes(minutes))
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+
+        let mut histogram = self.histogram.clone();
+        if let Some(pending) = self.parser.pending() {
+            if let Some(minute) = pending.timestamp.as_deref().and_then(minute_key) {
+                histogram.entry(minute).or_default().add(pending.level);
+            }
+        }
+
+        histogram
+            .range(cutoff..)
+            .map(|(minute, counts)| HistogramBucket {
+                minute: minute.clone(),
+                counts: *counts,
+            })
+            .collect()
+    }
+}
+
+static STATS: Mutex<LogStats> = Mutex::new(LogStats::empty());
+
+/// 从时间戳中取出分钟（"2025-01-01 12:00:00.123" -> "2025-01-01 12:00"）
+fn minute_key(timestamp: &str) -> Option<String> {
+    let minute = timestamp.get(..16)?.replacen('T', " ", 1);
+    chrono::NaiveDateTime::parse_from_str(&format!("{}:00", minute), "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(minute)
+}
+
+/// 更新并返回各级别计数
+pub fn level_counts() -> Result<LevelCounts, String> {
+    let path = logger::get_log_path().map_err(|e| e.to_string())?;
+    let mut stats = STATS.lock().unwrap();
+
+    stats.update(&path)
+        .map_err(|e| format!("读取日志文件失败: {}", e))?;
+
+    Ok(stats.snapshot())
+}
+
+/// 更新并返回最近 minutes 分钟的直方图（最多 24 小时）
+pub fn histogram(minutes: Option<u32>) -> Result<Vec<HistogramBucket>, String> {
+    let path = logger::get_log_path().map_err(|e| e.to_string())?;
+    let minutes = minutes.map(|m| m as i64).unwrap_or(HISTOGRAM_MINUTES).clamp(1, HISTOGRAM_MINUTES);
+    let mut stats = STATS.lock().unwrap();
+
+    stats.update(&path)
+        .map_err(|e| format!("读取日志文件失败: {}", e))?;
+
+    Ok(stats.histogram(minutes))
+}
+
+/// 获取按分钟统计的日志级别直方图
+#[tauri::command]
+pub async fn get_log_histogram(minutes: Option<u32>) -> Result<Vec<HistogramBucket>, String> {
+    tokio::task::spawn_blocking(move || histogram(minutes))
+        .await
+        .map_err(|e| format!("获取日志直方图失败: {}", e))?
+}
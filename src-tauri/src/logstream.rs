@@ -0,0 +1,426 @@
+// 日志流模块
+// 所有订阅者共享一个跟踪日志文件的任务，每个订阅者（窗口或标签页）有自己的订阅 ID 和过滤条件。
+// 新增记录放入各订阅者自己的队列，不会丢弃：某个订阅者处理不过来（如窗口卡住）时只是它的队列变长，
+// 不影响其他订阅者（告警、活动动态等）。每个订阅者的推送任务取出队列中积压的批次合并、过滤后批量推送。
+// 解析器状态跨批次保留，跨两次读取的堆栈仍归入同一条记录。
+// 脱敏在各订阅者的推送任务中按所属窗口进行，某个窗口显示原文不影响其他窗口和告警、活动等内部订阅。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, State};
+use tokio::sync::mpsc;
+
+use crate::logger::{self, FileTracker};
+use crate::logparse::{LogParser, LogRecord};
+use crate::logquery::{CompiledFilter, LogFilter};
+use crate::logredact;
+
+/// 没有文件事件时的轮询间隔，防止 watcher 漏掉某些事件
+const POLL_INTERVAL: Duration = Duration::from_millis(2000);
+
+/// 最后一条记录可能还有后续行（如堆栈），超过这么久没有新内容才输出
+const IDLE_FLUSH: Duration = Duration::from_millis(300);
+
+/// 默认合并间隔（毫秒）
+const DEFAULT_BATCH_MS: u64 = 250;
+const MAX_BATCH_MS: u64 = 5000;
+
+/// 默认单批最多记录数
+const DEFAULT_MAX_BATCH: usize = 500;
+const MAX_BATCH_LIMIT: usize = 5000;
+
+/// 订阅选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOptions {
+    #[serde(flatten)]
+    pub filter: LogFilter,
+    /// 合并间隔，两次推送之间至少间隔这么久
+    pub batch_ms: Option<u64>,
+    /// 单批最多记录数，超出时拆成多批
+    pub max_batch: Option<usize>,
+}
+
+/// 推送给订阅者的一批记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamBatch {
+    pub subscription_id: u64,
+    pub records: Vec<LogRecord>,
+}
+
+/// 订阅者
+struct Subscriber {
+    /// 所属窗口，内部订阅使用不会与窗口重名的标签（始终脱敏）
+    label: String,
+    /// 旧版 start_log_stream 创建的订阅，推送 log-update / log-records 事件
+    legacy: bool,
+    sender: mpsc::UnboundedSender<Arc<Vec<LogRecord>>>,
+}
+
+struct HubInner {
+    subscribers: Mutex<HashMap<u64, Subscriber>>,
+    next_id: AtomicU64,
+    tailer: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl HubInner {
+    /// 移除订阅，丢弃发送端后推送任务会发完剩余内容再退出；没有订阅者时停止跟踪
+    fn remove(&self, id: u64) -> bool {
+        self.remove_where(|sub_id, _| sub_id == id) > 0
+    }
+
+    /// 移除满足条件的订阅，返回移除的数量
+    fn remove_where<P>(&self, predicate: P) -> usize
+    where
+        P: Fn(u64, &Subscriber) -> bool,
+    {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let before = subscribers.len();
+        subscribers.retain(|id, subscriber| !predicate(*id, subscriber));
+        let removed = before - subscribers.len();
+
+        if subscribers.is_empty() {
+            if let Some(task) = self.tailer.lock().unwrap().take() {
+                task.abort();
+            }
+        }
+
+        removed
+    }
+
+    /// 把一批记录放入所有订阅者的队列，不等待处理不过来的订阅者
+    fn dispatch(&self, records: Arc<Vec<LogRecord>>) {
+        let subscribers = self.subscribers.lock().unwrap();
+
+        for subscriber in subscribers.values() {
+            // 发送失败说明推送任务已退出，随后会移除该订阅
+            let _ = subscriber.sender.send(records.clone());
+        }
+    }
+}
+
+/// 日志流订阅管理
+pub struct LogStreamHub {
+    inner: Arc<HubInner>,
+}
+
+impl LogStreamHub {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(HubInner {
+                subscribers: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(1),
+                tailer: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// 添加订阅，emit 负责把一批记录推送出去，返回错误时（如窗口已关闭）订阅自动取消
+    pub fn subscribe<F>(&self, label: &str, options: StreamOptions, legacy: bool, emit: F) -> Result<u64, String>
+    where
+        F: Fn(&StreamBatch) -> Result<(), String> + Send + 'static,
+    {
+        let delivery = Delivery {
+            label: label.to_string(),
+            filter: options.filter.compile()?,
+            batch_interval: Duration::from_millis(options.batch_ms.unwrap_or(DEFAULT_BATCH_MS).min(MAX_BATCH_MS)),
+            max_batch: options.max_batch.unwrap_or(DEFAULT_MAX_BATCH).clamp(1, MAX_BATCH_LIMIT),
+        };
+
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        {
+            let mut subscribers = self.inner.subscribers.lock().unwrap();
+            subscribers.insert(id, Subscriber {
+                label: label.to_string(),
+                legacy,
+                sender,
+            });
+        }
+
+        if let Err(e) = self.ensure_tailer() {
+            self.inner.remove(id);
+            return Err(e);
+        }
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            deliver(id, receiver, delivery, emit).await;
+            inner.remove(id);
+        });
+
+        Ok(id)
+    }
+
+    /// 取消订阅
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        self.inner.remove(id)
+    }
+
+    /// 取消窗口的所有订阅（窗口关闭或重新加载时），返回取消的数量
+    pub fn unsubscribe_window(&self, label: &str) -> usize {
+        self.inner.remove_where(|_, subscriber| subscriber.label == label)
+    }
+
+    /// 窗口的旧版订阅
+    fn legacy_subscription(&self, label: &str) -> Option<u64> {
+        self.inner
+            .subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, s)| s.legacy && s.label == label)
+            .map(|(id, _)| *id)
+    }
+
+    /// 没有运行中的跟踪任务时启动一个，从文件当前末尾开始
+    fn ensure_tailer(&self) -> Result<(), String> {
+        let mut tailer = self.inner.tailer.lock().unwrap();
+        if tailer.as_ref().map(|t| !t.is_finished()).unwrap_or(false) {
+            return Ok(());
+        }
+
+        let log_path = logger::get_log_path().map_err(|e| e.to_string())?;
+
+        // 如果日志文件不存在，创建它
+        if !log_path.exists() {
+            if let Some(parent) = log_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("创建日志目录失败: {}", e))?;
+            }
+            std::fs::File::create(&log_path)
+                .map_err(|e| format!("创建日志文件失败: {}", e))?;
+        }
+
+        let tracker = FileTracker::at_end(&log_path);
+        *tailer = Some(tokio::spawn(run_tailer(self.inner.clone(), log_path, tracker)));
+        Ok(())
+    }
+}
+
+/// 跟踪日志文件：文件事件或定时轮询唤醒后读取新增行，解析后分发给所有订阅者
+async fn run_tailer(inner: Arc<HubInner>, log_path: std::path::PathBuf, mut tracker: FileTracker) {
+    use notify::{recommended_watcher, EventKind, RecursiveMode, Watcher};
+
+    let (wake_tx, mut wake_rx) = mpsc::unbounded_channel::<()>();
+    // 保留一个发送端，watcher 创建失败时 recv 不会立即返回 None
+    let _wake_tx = wake_tx.clone();
+    let file_name = log_path.file_name().map(|n| n.to_os_string());
+
+    // 监控日志目录而不是文件本身，文件被轮转替换后仍能收到事件
+    let watcher = recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let is_log_file = event.paths.iter().any(|p| p.file_name() == file_name.as_deref());
+            if is_log_file && matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                let _ = wake_tx.send(());
+            }
+        }
+    });
+
+    // watcher 不可用时仅靠轮询
+    let _watcher = match watcher {
+        Ok(mut watcher) => {
+            let log_dir = log_path.parent().unwrap_or(Path::new("."));
+            match watcher.watch(log_dir, RecursiveMode::NonRecursive) {
+                Ok(_) => Some(watcher),
+                Err(e) => {
+                    log::warn!("监控日志目录失败，改为轮询: {}", e);
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            log::warn!("创建文件监控器失败，改为轮询: {}", e);
+            None
+        }
+    };
+
+    let mut parser = LogParser::new();
+
+    loop {
+        // 有未完成的记录时缩短等待，空闲后输出
+        let wait = if parser.pending().is_some() { IDLE_FLUSH } else { POLL_INTERVAL };
+
+        tokio::select! {
+            _ = wake_rx.recv() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        // 合并已经排队的唤醒
+        while wake_rx.try_recv().is_ok() {}
+
+        let lines = match logger::read_new_lines(&mut tracker) {
+            Ok(lines) => lines,
+            Err(e) => {
+                log::warn!("读取新日志失败: {}", e);
+                continue;
+            }
+        };
+
+        let mut records: Vec<LogRecord> = lines.iter().filter_map(|line| parser.push(line)).collect();
+        if lines.is_empty() {
+            records.extend(parser.flush());
+        }
+
+        if records.is_empty() {
+            continue;
+        }
+
+        inner.dispatch(Arc::new(records));
+    }
+}
+
+/// 单个订阅者的推送设置
+struct Delivery {
+    /// 所属窗口，按该窗口是否显示原文脱敏
+    label: String,
+    filter: CompiledFilter,
+    batch_interval: Duration,
+    max_batch: usize,
+}
+
+/// 单个订阅者的推送任务：收到第一批后在合并间隔内继续收集，脱敏、过滤后按 max_batch 分批推送
+async fn deliver<F>(
+    id: u64,
+    mut receiver: mpsc::UnboundedReceiver<Arc<Vec<LogRecord>>>,
+    delivery: Delivery,
+    emit: F,
+) where
+    F: Fn(&StreamBatch) -> Result<(), String>,
+{
+    let Delivery { label, filter, batch_interval, max_batch } = delivery;
+    let mut closed = false;
+
+    // 先脱敏再过滤，过滤条件无法匹配到被隐藏的内容
+    let prepare = |batch: &[LogRecord], records: &mut Vec<LogRecord>| {
+        let redactor = logredact::for_display(&label);
+        records.extend(
+            batch
+                .iter()
+                .cloned()
+                .map(|mut record| {
+                    if let Some(redactor) = &redactor {
+                        redactor.redact_record(&mut record);
+                    }
+                    record
+                })
+                .filter(|r| filter.matches(r)),
+        );
+    };
+
+    loop {
+        let Some(first) = receiver.recv().await else {
+            break;
+        };
+
+        let mut records: Vec<LogRecord> = Vec::new();
+        prepare(&first, &mut records);
+        let deadline = tokio::time::Instant::now() + batch_interval;
+
+        while records.len() < max_batch {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(batch)) => prepare(&batch, &mut records),
+                Ok(None) => {
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        for chunk in records.chunks(max_batch) {
+            let batch = StreamBatch {
+                subscription_id: id,
+                records: chunk.to_vec(),
+            };
+
+            if let Err(e) = emit(&batch) {
+                log::warn!("推送日志订阅 {} 失败，取消订阅: {}", id, e);
+                return;
+            }
+        }
+
+        if closed {
+            break;
+        }
+
+        // 节流：两次推送之间至少间隔 batch_interval
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+/// 订阅日志流，记录通过 log-stream 事件推送到当前窗口
+#[tauri::command]
+pub async fn subscribe_logs(
+    window: tauri::Window,
+    options: Option<StreamOptions>,
+    hub: State<'_, LogStreamHub>,
+) -> Result<u64, String> {
+    let label = window.label().to_string();
+    let target = label.clone();
+
+    hub.subscribe(&label, options.unwrap_or_default(), false, move |batch| {
+        window.emit_to(target.as_str(), "log-stream", batch)
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// 取消日志流订阅
+#[tauri::command]
+pub async fn unsubscribe_logs(subscription_id: u64, hub: State<'_, LogStreamHub>) -> Result<(), String> {
+    if hub.unsubscribe(subscription_id) {
+        Ok(())
+    } else {
+        Err(format!("日志订阅 {} 不存在", subscription_id))
+    }
+}
+
+/// 启动日志流（旧版接口）：不带过滤条件，推送 log-update（原始行）和 log-records（解析后的记录）
+#[tauri::command]
+pub async fn start_log_stream(window: tauri::Window, hub: State<'_, LogStreamHub>) -> Result<(), String> {
+    let label = window.label().to_string();
+
+    // 检查是否已经在运行
+    if hub.legacy_subscription(&label).is_some() {
+        return Ok(());
+    }
+
+    let target = label.clone();
+    let options = StreamOptions {
+        batch_ms: Some(100),
+        ..Default::default()
+    };
+
+    hub.subscribe(&label, options, true, move |batch| {
+        let lines: Vec<&str> = batch.records.iter().flat_map(|r| r.raw_lines()).collect();
+        window.emit_to(target.as_str(), "log-update", lines)
+            .and_then(|_| window.emit_to(target.as_str(), "log-records", &batch.records))
+            .map_err(|e| e.to_string())
+    })?;
+
+    Ok(())
+}
+
+/// 停止日志流（旧版接口）
+#[tauri::command]
+pub async fn stop_log_stream(window: tauri::Window, hub: State<'_, LogStreamHub>) -> Result<(), String> {
+    match hub.legacy_subscription(window.label()) {
+        Some(id) => {
+            hub.unsubscribe(id);
+            Ok(())
+        }
+        None => Err("没有正在运行的日志监控".to_string()),
+    }
+}
+
+/// 检查日志流是否正在运行（旧版接口）
+#[tauri::command]
+pub async fn is_log_stream_running(window: tauri::Window, hub: State<'_, LogStreamHub>) -> Result<bool, String> {
+    Ok(hub.legacy_subscription(window.label()).is_some())
+}
@@ -12,9 +12,28 @@ mod theme;
 mod menu;
 mod cron;
 mod clawhub;
+mod fsutil;
+mod profile;
+mod secrets;
+mod provider;
+mod mcp;
+mod channel;
+mod whatsapp;
+mod logparse;
+mod logquery;
+mod logstats;
+mod logfiles;
+mod logrotate;
+mod logstream;
+mod alerts;
+mod logissues;
+mod logredact;
+mod logexport;
+mod activity;
+mod usage;
+mod applog;
 
 use std::sync::Mutex;
-use std::sync::Arc;
 
 struct AppState {
     config_path: Mutex<Option<String>>,
@@ -23,18 +42,21 @@ struct AppState {
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    applog::init();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(AppState {
             config_path: Mutex::new(None),
             nanobot_process: Mutex::new(None),
         })
-        .manage(Arc::new(tokio::sync::Mutex::new(logger::FileTracker::new())))
-        .manage(Arc::new(logger::WatcherHandle::new()))
+        .manage(logstream::LogStreamHub::new())
+        .manage(alerts::AlertEngine::new())
+        .manage(activity::ActivityFeed::new())
         .manage(std::sync::Mutex::new(network::NetworkMonitor::new()))
         .manage(theme::ThemeState::new())
+        .manage(whatsapp::WhatsAppLoginState::new())
         .setup(|app| {
             // 设置窗口图标
             if let Some(window) = app.get_webview_window("main") {
@@ -52,6 +74,19 @@ async fn main() {
             #[cfg(not(target_os = "macos"))]
             menu::setup_tray(app_handle)?;
 
+            // 有启用的告警规则时开始监控日志
+            let engine = app.state::<alerts::AlertEngine>();
+            let hub = app.state::<logstream::LogStreamHub>();
+            if let Err(e) = alerts::sync(app.handle(), &engine, &hub) {
+                log::warn!("启动日志告警失败: {}", e);
+            }
+
+            // 从 gateway 日志中解析活动动态
+            let feed = app.state::<activity::ActivityFeed>();
+            if let Err(e) = activity::start(app.handle(), &feed, &hub) {
+                log::warn!("启动活动动态失败: {}", e);
+            }
+
             // 监听菜单事件
             let app_handle = app.handle().clone();
             let app_handle_for_menu = app_handle.clone();
@@ -62,6 +97,19 @@ async fn main() {
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // 窗口关闭后取消该窗口的日志订阅，并恢复该窗口的日志脱敏
+            if let tauri::WindowEvent::Destroyed = event {
+                window.state::<logstream::LogStreamHub>().unsubscribe_window(window.label());
+                logredact::forget_window(window.label());
+            }
+        })
+        .on_page_load(|webview, payload| {
+            // 页面重新加载后前端会重新订阅，取消上一个页面留下的订阅
+            if let tauri::webview::PageLoadEvent::Started = payload.event() {
+                webview.state::<logstream::LogStreamHub>().unsubscribe_window(webview.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Config commands
             config::load_config,
@@ -71,6 +119,41 @@ async fn main() {
             config::get_config_history,
             config::restore_config_version,
             config::delete_config_version,
+            config::get_config_value,
+            config::set_config_value,
+            config::delete_config_value,
+            config::patch_config,
+            config::export_config,
+            config::preview_config_import,
+            config::import_config,
+            config::get_config_migrations,
+            config::apply_config_migrations,
+            secrets::reveal_config_secret,
+            // Profile commands
+            profile::create_config_profile,
+            profile::list_config_profiles,
+            profile::rename_config_profile,
+            profile::delete_config_profile,
+            profile::diff_config_profile,
+            profile::activate_config_profile,
+            // Provider commands
+            provider::test_provider_connectivity,
+            provider::get_model_catalog,
+            provider::discover_local_llm_servers,
+            provider::configure_local_provider,
+            // MCP commands
+            mcp::list_mcp_servers,
+            mcp::add_mcp_server,
+            mcp::update_mcp_server,
+            mcp::remove_mcp_server,
+            mcp::test_mcp_server,
+            // Channel commands
+            channel::list_channels,
+            channel::set_channel_enabled,
+            channel::set_channel_allow_from,
+            whatsapp::start_whatsapp_login,
+            whatsapp::get_whatsapp_login_status,
+            whatsapp::cancel_whatsapp_login,
             // Process commands
             process::start_nanobot,
             process::stop_nanobot,
@@ -91,10 +174,39 @@ async fn main() {
             process::get_python_path,
             // Logger commands
             logger::get_logs,
+            logquery::query_logs,
+            logfiles::list_log_files,
+            logrotate::get_log_rotation_settings,
+            logrotate::set_log_rotation_settings,
             logger::get_log_statistics,
-            logger::start_log_stream,
-            logger::stop_log_stream,
-            logger::is_log_stream_running,
+            logstats::get_log_histogram,
+            logissues::get_error_groups,
+            logexport::export_logs,
+            logredact::get_log_secrets_revealed,
+            logredact::set_log_secrets_revealed,
+            logstream::start_log_stream,
+            logstream::stop_log_stream,
+            logstream::is_log_stream_running,
+            logstream::subscribe_logs,
+            logstream::unsubscribe_logs,
+            // Alert commands
+            alerts::list_alert_rules,
+            alerts::save_alert_rule,
+            alerts::delete_alert_rule,
+            alerts::get_alert_history,
+            alerts::acknowledge_alerts,
+            // Activity commands
+            activity::get_activity,
+            // Usage commands
+            usage::query_usage,
+            usage::export_usage_csv,
+            usage::get_usage_prices,
+            usage::set_usage_prices,
+            // App log commands
+            applog::get_app_log_level,
+            applog::set_app_log_level,
+            applog::read_app_log,
+            applog::tail_app_log,
             // Network commands
             network::init_network_monitor,
             network::get_network_stats,
@@ -0,0 +1,763 @@
+// MCP 服务器管理模块
+// 管理 config.json 中 tools.mcpServers 下的条目，并支持通过 initialize 握手测试服务器可用性
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+use crate::config;
+use crate::process;
+use crate::secrets;
+
+/// MCP 协议版本
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// tools/list 最多翻页次数，防止异常服务器无限返回 nextCursor
+const MAX_TOOL_PAGES: usize = 20;
+
+/// 保留的 stderr 字节数，用于错误提示
+const STDERR_TAIL_BYTES: usize = 4096;
+
+/// MCP 服务器条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerEntry {
+    pub name: String,
+    /// stdio 或 http
+    pub transport: String,
+    /// 配置内容（密钥已打码）
+    pub config: JsonValue,
+}
+
+/// MCP 测试结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTestResult {
+    pub ok: bool,
+    pub transport: String,
+    pub protocol_version: Option<String>,
+    pub server_info: Option<JsonValue>,
+    pub capabilities: Option<JsonValue>,
+    pub tools: Vec<JsonValue>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    /// stdio 服务器 stderr 的末尾内容
+    pub stderr: Option<String>,
+}
+
+/// 生成服务器条目的 JSON Pointer
+fn server_pointer(name: &str) -> String {
+    format!("/tools/mcpServers/{}", name.replace('~', "~0").replace('/', "~1"))
+}
+
+/// 判断传输方式
+fn transport_of(server: &JsonValue) -> &'static str {
+    if server.get("url").and_then(|v| v.as_str()).map(|s| !s.is_empty()).unwrap_or(false) {
+        "http"
+    } else {
+        "stdio"
+    }
+}
+
+/// 验证服务器名称
+fn validate_server_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("MCP 服务器名称不能为空".to_string());
+    }
+
+    if name.len() > 64 {
+        return Err("MCP 服务器名称不能超过 64 个字符".to_string());
+    }
+
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err("MCP 服务器名称只能包含字母、数字、连字符、下划线和点".to_string());
+    }
+
+    Ok(())
+}
+
+/// 验证服务器配置
+/// 必须且只能指定 command（stdio）或 url（HTTP）之一
+fn validate_server_config(server: &JsonValue) -> Result<(), String> {
+    let obj = server.as_object().ok_or_else(|| "MCP 服务器配置必须是对象".to_string())?;
+
+    let has_command = obj.get("command").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false);
+    let has_url = obj.get("url").and_then(|v| v.as_str()).map(|s| !s.trim().is_empty()).unwrap_or(false);
+
+    match (has_command, has_url) {
+        (false, false) => return Err("MCP 服务器需要指定 command 或 url".to_string()),
+        (true, true) => return Err("command 和 url 不能同时指定".to_string()),
+        _ => {}
+    }
+
+    if has_url {
+        let url = obj["url"].as_str().unwrap_or_default();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("无效的 MCP 服务器地址: {}", url));
+        }
+    }
+
+    if let Some(args) = obj.get("args") {
+        let valid = args.as_array().map(|a| a.iter().all(|v| v.is_string())).unwrap_or(false);
+        if !valid {
+            return Err("args 必须是字符串数组".to_string());
+        }
+    }
+
+    for field in ["env", "headers"] {
+        if let Some(map) = obj.get(field) {
+            let valid = map.as_object().map(|m| m.values().all(|v| v.is_string())).unwrap_or(false);
+            if !valid {
+                return Err(format!("{} 必须是字符串键值对", field));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取配置中的 MCP 服务器（未打码）
+fn read_server(name: &str) -> Result<JsonValue, String> {
+    let config = config::read_config_file()?;
+
+    config
+        .pointer(&server_pointer(name))
+        .cloned()
+        .ok_or_else(|| format!("MCP 服务器 {} 不存在", name))
+}
+
+/// 列出所有 MCP 服务器
+#[tauri::command]
+pub async fn list_mcp_servers() -> Result<Vec<McpServerEntry>, String> {
+    let config = match config::read_config_file() {
+        Ok(config) => config,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut servers: Vec<McpServerEntry> = config
+        .pointer("/tools/mcpServers")
+        .and_then(|v| v.as_object())
+        .map(|map| {
+            map.iter()
+                .map(|(name, server)| McpServerEntry {
+                    name: name.clone(),
+                    transport: transport_of(server).to_string(),
+                    config: secrets::mask_secrets(server),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(servers)
+}
+
+/// 添加 MCP 服务器
+#[tauri::command]
+pub async fn add_mcp_server(name: String, server: JsonValue) -> Result<JsonValue, String> {
+    validate_server_name(&name)?;
+    validate_server_config(&server)?;
+
+    let pointer = server_pointer(&name);
+
+    let validation = config::update_config(|config| {
+        if config.pointer(&pointer).is_some() {
+            return Err(format!("MCP 服务器 {} 已存在", name));
+        }
+        config::set_value_at_pointer(config, &pointer, server)
+    })?;
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已添加 MCP 服务器 {}", name),
+        "validation": validation
+    }))
+}
+
+/// 编辑 MCP 服务器
+/// 指定 new_name 时同时重命名
+#[tauri::command]
+pub async fn update_mcp_server(
+    name: String,
+    server: JsonValue,
+    new_name: Option<String>,
+) -> Result<JsonValue, String> {
+    validate_server_config(&server)?;
+
+    let target = new_name.unwrap_or_else(|| name.clone());
+    validate_server_name(&target)?;
+
+    let pointer = server_pointer(&name);
+    let target_pointer = server_pointer(&target);
+
+    // 前端回传的是打码后的 env/headers，重命名时需要按原路径还原
+    let mut server = server;
+    if let Ok(current) = read_server(&name) {
+        secrets::unmask_secrets(&mut server, &current);
+    }
+
+    let validation = config::update_config(|config| {
+        if config.pointer(&pointer).is_none() {
+            return Err(format!("MCP 服务器 {} 不存在", name));
+        }
+
+        if target != name {
+            if config.pointer(&target_pointer).is_some() {
+                return Err(format!("MCP 服务器 {} 已存在", target));
+            }
+            config::remove_value_at_pointer(config, &pointer)?;
+        }
+
+        config::set_value_at_pointer(config, &target_pointer, server)
+    })?;
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已更新 MCP 服务器 {}", target),
+        "validation": validation
+    }))
+}
+
+/// 删除 MCP 服务器
+#[tauri::command]
+pub async fn remove_mcp_server(name: String) -> Result<JsonValue, String> {
+    let pointer = server_pointer(&name);
+
+    let validation = config::update_config(|config| {
+        if config.pointer(&pointer).is_none() {
+            return Err(format!("MCP 服务器 {} 不存在", name));
+        }
+        config::remove_value_at_pointer(config, &pointer).map(|_| ())
+    })?;
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已删除 MCP 服务器 {}", name),
+        "validation": validation
+    }))
+}
+
+/// 测试 MCP 服务器
+/// 指定 name 时测试已保存的条目，否则测试传入的 server 配置（用于保存前验证）
+#[tauri::command]
+pub async fn test_mcp_server(
+    name: Option<String>,
+    server: Option<JsonValue>,
+    timeout_secs: Option<u64>,
+) -> Result<McpTestResult, String> {
+    let server = match (name, server) {
+        // 编辑中的条目：还原前端回传的打码值
+        (Some(name), Some(mut server)) => {
+            if let Ok(current) = read_server(&name) {
+                secrets::unmask_secrets(&mut server, &current);
+            }
+            server
+        }
+        (None, Some(server)) => server,
+        (Some(name), None) => read_server(&name)?,
+        (None, None) => return Err("需要指定 MCP 服务器名称或配置".to_string()),
+    };
+
+    validate_server_config(&server)?;
+
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(30));
+    let transport = transport_of(&server);
+    let started = Instant::now();
+
+    let mut result = match transport {
+        "http" => test_http_server(&server, timeout).await,
+        _ => test_stdio_server(&server, timeout).await,
+    };
+
+    result.transport = transport.to_string();
+    result.latency_ms = started.elapsed().as_millis() as u64;
+
+    Ok(result)
+}
+
+/// 构造 JSON-RPC 请求
+fn rpc_request(id: u64, method: &str, params: JsonValue) -> JsonValue {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn initialize_params() -> JsonValue {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "nanoboard", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+/// 从 JSON-RPC 响应中取出 result，错误响应转换为错误信息
+fn rpc_result(response: JsonValue) -> Result<JsonValue, String> {
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(|v| v.as_str()).unwrap_or("未知错误");
+        let code = error.get("code").and_then(|v| v.as_i64()).unwrap_or(0);
+        return Err(format!("服务器返回错误 {}: {}", code, message));
+    }
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| "响应中缺少 result".to_string())
+}
+
+/// MCP 传输层
+enum McpTransport {
+    Stdio {
+        stdin: tokio::process::ChildStdin,
+        lines: tokio::io::Lines<BufReader<tokio::process::ChildStdout>>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        headers: Vec<(String, String)>,
+        session_id: Option<String>,
+    },
+}
+
+impl McpTransport {
+    /// 发送消息并返回对应 id 的响应，通知（无 id）返回 None
+    async fn send(&mut self, message: JsonValue) -> Result<Option<JsonValue>, String> {
+        let id = message.get("id").cloned();
+
+        match self {
+            McpTransport::Stdio { stdin, lines } => {
+                let line = format!("{}\n", message);
+                stdin.write_all(line.as_bytes()).await.map_err(|e| format!("写入 stdin 失败: {}", e))?;
+                stdin.flush().await.map_err(|e| format!("写入 stdin 失败: {}", e))?;
+
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+
+                // 跳过通知、服务器请求和非 JSON 输出，直到读到对应 id 的响应
+                loop {
+                    let line = lines
+                        .next_line()
+                        .await
+                        .map_err(|e| format!("读取 stdout 失败: {}", e))?
+                        .ok_or_else(|| "服务器进程已退出".to_string())?;
+
+                    let Ok(response) = serde_json::from_str::<JsonValue>(line.trim()) else {
+                        continue;
+                    };
+
+                    if response.get("id") == Some(&id) && response.get("method").is_none() {
+                        return Ok(Some(response));
+                    }
+                }
+            }
+            McpTransport::Http { client, url, headers, session_id } => {
+                let mut request = client
+                    .post(url.as_str())
+                    .header("Accept", "application/json, text/event-stream")
+                    .json(&message);
+
+                for (key, value) in headers.iter() {
+                    request = request.header(key.as_str(), value.as_str());
+                }
+                if let Some(session) = session_id.as_deref() {
+                    request = request.header("Mcp-Session-Id", session);
+                }
+
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| format!("请求 {} 失败: {}", url, e))?;
+
+                if let Some(session) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+                    *session_id = Some(session.to_string());
+                }
+
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+
+                if !status.is_success() {
+                    let summary: String = body.chars().take(200).collect();
+                    return Err(format!("HTTP {}: {}", status.as_u16(), summary.trim()));
+                }
+
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+
+                // 响应可能是 JSON，也可能是 text/event-stream 中的 data 行
+                if let Ok(response) = serde_json::from_str::<JsonValue>(body.trim()) {
+                    return Ok(Some(response));
+                }
+
+                body.lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .filter_map(|data| serde_json::from_str::<JsonValue>(data.trim()).ok())
+                    .find(|response| response.get("id") == Some(&id))
+                    .map(Some)
+                    .ok_or_else(|| "响应中没有对应的 JSON-RPC 结果".to_string())
+            }
+        }
+    }
+
+    /// 发送请求并取出 result
+    async fn request(&mut self, id: u64, method: &str, params: JsonValue) -> Result<JsonValue, String> {
+        match self.send(rpc_request(id, method, params)).await? {
+            Some(response) => rpc_result(response),
+            None => Err(format!("{} 没有响应", method)),
+        }
+    }
+}
+
+/// 依次执行 initialize、notifications/initialized、tools/list
+async fn run_handshake(transport: &mut McpTransport) -> McpTestResult {
+    let mut result = McpTestResult::default();
+
+    let init = match transport.request(1, "initialize", initialize_params()).await {
+        Ok(init) => init,
+        Err(e) => {
+            result.error = Some(format!("initialize 失败: {}", e));
+            return result;
+        }
+    };
+
+    result.protocol_version = init.get("protocolVersion").and_then(|v| v.as_str()).map(|s| s.to_string());
+    result.server_info = init.get("serverInfo").cloned();
+    result.capabilities = init.get("capabilities").cloned();
+
+    if let Err(e) = transport.send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await {
+        result.error = Some(format!("发送 initialized 通知失败: {}", e));
+        return result;
+    }
+
+    let mut cursor: Option<String> = None;
+
+    for page in 0..MAX_TOOL_PAGES {
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+
+        let listed = match transport.request(2 + page as u64, "tools/list", params).await {
+            Ok(listed) => listed,
+            Err(e) => {
+                result.error = Some(format!("获取工具列表失败: {}", e));
+                return result;
+            }
+        };
+
+        if let Some(tools) = listed.get("tools").and_then(|v| v.as_array()) {
+            result.tools.extend(tools.iter().map(|tool| {
+                json!({
+                    "name": tool.get("name").cloned().unwrap_or(JsonValue::Null),
+                    "description": tool.get("description").cloned().unwrap_or(JsonValue::Null)
+                })
+            }));
+        }
+
+        cursor = listed.get("nextCursor").and_then(|v| v.as_str()).map(|s| s.to_string());
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    result.ok = true;
+    result
+}
+
+/// 测试 stdio 服务器：启动子进程，按行收发 JSON-RPC 消息
+async fn test_stdio_server(server: &JsonValue, timeout: Duration) -> McpTestResult {
+    let command = server["command"].as_str().unwrap_or_default().trim().to_string();
+
+    // GUI 应用的 PATH 可能不完整，优先使用查找到的完整路径（npx、uvx 等）
+    let program = process::find_command(&command).unwrap_or(command.clone());
+
+    let args: Vec<String> = server
+        .get("args")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+
+    let mut cmd = tokio::process::Command::new(&program);
+    cmd.args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    if let Some(env) = server.get("env").and_then(|v| v.as_object()) {
+        for (key, value) in env {
+            if let Some(value) = value.as_str() {
+                cmd.env(key, value);
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return failed_result(format!("启动 {} 失败: {}", command, e)),
+    };
+
+    let (Some(stdin), Some(stdout), Some(mut stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+        return failed_result("无法获取子进程的标准输入输出".to_string());
+    };
+
+    // 后台收集 stderr，避免缓冲区写满阻塞子进程
+    let stderr_task = tokio::spawn(async move {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf).await;
+        buf
+    });
+
+    let mut transport = McpTransport::Stdio {
+        stdin,
+        lines: BufReader::new(stdout).lines(),
+    };
+
+    let mut result = match tokio::time::timeout(timeout, run_handshake(&mut transport)).await {
+        Ok(result) => result,
+        Err(_) => failed_result(format!("等待服务器响应超时（{} 秒）", timeout.as_secs())),
+    };
+
+    drop(transport);
+    let _ = child.kill().await;
+
+    if let Ok(Ok(buf)) = tokio::time::timeout(Duration::from_secs(1), stderr_task).await {
+        let start = buf.len().saturating_sub(STDERR_TAIL_BYTES);
+        let tail = String::from_utf8_lossy(&buf[start..]).trim().to_string();
+        if !tail.is_empty() {
+            result.stderr = Some(tail);
+        }
+    }
+
+    result
+}
+
+/// 测试 HTTP 服务器（Streamable HTTP 传输）
+async fn test_http_server(server: &JsonValue, timeout: Duration) -> McpTestResult {
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => return failed_result(format!("创建 HTTP 客户端失败: {}", e)),
+    };
+
+    let headers: Vec<(String, String)> = server
+        .get("headers")
+        .and_then(|v| v.as_object())
+        .map(|m| {
+            m.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut transport = McpTransport::Http {
+        client,
+        url: server["url"].as_str().unwrap_or_default().trim().to_string(),
+        headers,
+        session_id: None,
+    };
+
+    match tokio::time::timeout(timeout, run_handshake(&mut transport)).await {
+        Ok(result) => result,
+        Err(_) => failed_result(format!("等待服务器响应超时（{} 秒）", timeout.as_secs())),
+    }
+}
+
+fn failed_result(error: String) -> McpTestResult {
+    McpTestResult {
+        error: Some(error),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    /// 按行读写 JSON-RPC 的 stdio 服务器：先输出非 JSON 的横幅和 stderr，工具列表分两页返回
+    const STDIO_STUB: &str = r#"
+import sys, json
+sys.stderr.write("stub starting\n"); sys.stderr.flush()
+print("not json banner", flush=True)
+for line in sys.stdin:
+    msg = json.loads(line)
+    if "id" not in msg:
+        continue
+    if msg["method"] == "initialize":
+        print(json.dumps({"jsonrpc": "2.0", "method": "notifications/message", "params": {}}), flush=True)
+        res = {"protocolVersion": "2024-11-05", "capabilities": {"tools": {}}, "serverInfo": {"name": "stub", "version": "1"}}
+    elif msg["method"] == "tools/list":
+        if msg["params"].get("cursor"):
+            res = {"tools": [{"name": "b", "description": "B"}]}
+        else:
+            res = {"tools": [{"name": "a", "description": "A"}], "nextCursor": "p2"}
+    else:
+        res = {}
+    print(json.dumps({"jsonrpc": "2.0", "id": msg["id"], "result": res}), flush=True)
+"#;
+
+    fn python() -> Option<String> {
+        process::find_command("python3")
+    }
+
+    #[tokio::test]
+    async fn stdio_handshake_lists_all_tool_pages() {
+        let Some(python) = python() else {
+            return;
+        };
+
+        let server = json!({ "command": python, "args": ["-c", STDIO_STUB] });
+        let result = test_stdio_server(&server, Duration::from_secs(10)).await;
+
+        assert!(result.ok, "{:?}", result.error);
+        assert_eq!(result.protocol_version.as_deref(), Some("2024-11-05"));
+        assert_eq!(result.server_info.as_ref().unwrap()["name"], "stub");
+        let names: Vec<&str> = result.tools.iter().filter_map(|t| t["name"].as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(result.stderr.as_deref(), Some("stub starting"));
+    }
+
+    #[tokio::test]
+    async fn stdio_reports_exited_server() {
+        let Some(python) = python() else {
+            return;
+        };
+
+        let server = json!({ "command": python, "args": ["-c", "import sys; sys.stderr.write('boom')"] });
+        let result = test_stdio_server(&server, Duration::from_secs(10)).await;
+
+        assert!(!result.ok);
+        assert!(result.error.unwrap().contains("initialize"));
+        assert_eq!(result.stderr.as_deref(), Some("boom"));
+    }
+
+    /// 读取一个 HTTP 请求，返回 (请求头, 请求体)
+    async fn read_http_request(socket: &mut tokio::net::TcpStream) -> (String, String) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return (head.to_lowercase(), body.to_string());
+                }
+            }
+        }
+
+        (String::new(), String::new())
+    }
+
+    /// Streamable HTTP 服务器：initialize 返回 JSON 并分配会话，之后的请求以 SSE 返回并校验会话头
+    async fn http_stub(seen_sessions: Arc<Mutex<Vec<Option<String>>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let (head, body) = read_http_request(&mut socket).await;
+                let message: JsonValue = serde_json::from_str(&body).unwrap_or_default();
+                let session = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("mcp-session-id:").map(|v| v.trim().to_string()));
+                seen_sessions.lock().unwrap().push(session);
+
+                let (content_type, payload) = match message["method"].as_str() {
+                    Some("initialize") => (
+                        "application/json",
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": message["id"],
+                            "result": { "protocolVersion": "2025-03-26", "capabilities": {}, "serverInfo": { "name": "http-stub" } }
+                        })
+                        .to_string(),
+                    ),
+                    Some("tools/list") => (
+                        "text/event-stream",
+                        format!(
+                            "event: message\ndata: {}\n\n",
+                            json!({ "jsonrpc": "2.0", "id": message["id"], "result": { "tools": [{ "name": "search" }] } })
+                        ),
+                    ),
+                    _ => ("application/json", String::new()),
+                };
+
+                let status = if payload.is_empty() { "202 Accepted" } else { "200 OK" };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nMcp-Session-Id: session-1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    payload.len(),
+                    payload
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        format!("http://{}/mcp", addr)
+    }
+
+    #[tokio::test]
+    async fn http_handshake_keeps_session_and_reads_event_stream() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let url = http_stub(seen.clone()).await;
+
+        let result = test_http_server(&json!({ "url": url }), Duration::from_secs(10)).await;
+
+        assert!(result.ok, "{:?}", result.error);
+        assert_eq!(result.protocol_version.as_deref(), Some("2025-03-26"));
+        assert_eq!(result.tools, vec![json!({ "name": "search", "description": null })]);
+
+        // initialize 没有会话，之后的通知和 tools/list 都带上服务器分配的会话
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0], None);
+        assert!(seen[1..].iter().all(|s| s.as_deref() == Some("session-1")));
+    }
+
+    #[tokio::test]
+    async fn http_reports_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_http_request(&mut socket).await;
+            let body = "unauthorized";
+            let response = format!(
+                "HTTP/1.1 401 Unauthorized\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let result = test_http_server(&json!({ "url": url }), Duration::from_secs(10)).await;
+
+        assert!(!result.ok);
+        let error = result.error.unwrap();
+        assert!(error.contains("HTTP 401"), "{}", error);
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::{AppHandle, Manager, Wry};
 use tauri::menu::{Menu, MenuItem, Submenu, PredefinedMenuItem};
 #[cfg(not(target_os = "macos"))]
@@ -61,6 +62,70 @@ pub fn build_menu(app: &AppHandle) -> Menu<Wry> {
     Menu::with_items(app, &[&file_menu, &edit_menu, &view_menu, &tools_menu, &help_menu]).unwrap()
 }
 
+/// 托盘图标 ID
+pub const TRAY_ID: &str = "main";
+
+/// 托盘图标当前是否为带角标的版本
+static TRAY_BADGED: AtomicBool = AtomicBool::new(false);
+
+/// 默认托盘图标
+fn tray_icon() -> tauri::image::Image<'static> {
+    // 使用专用的托盘图标 (22x22 适合 macOS 托盘)
+    let icon_bytes = include_bytes!("../icons/tray_icon.png");
+    tauri::image::Image::new(icon_bytes, 22, 22)
+}
+
+/// 有未读告警时的托盘图标：在应用图标右上角画一个红点
+fn badged_tray_icon(app: &AppHandle) -> Option<tauri::image::Image<'static>> {
+    let icon = app.default_window_icon()?;
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+
+    let radius = width.min(height) as f32 * 0.25;
+    let (cx, cy) = (width as f32 - radius, radius);
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 + 0.5 - cx;
+            let dy = y as f32 + 0.5 - cy;
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&[0xE5, 0x39, 0x35, 0xFF]);
+            }
+        }
+    }
+
+    Some(tauri::image::Image::new_owned(rgba, width, height))
+}
+
+/// 在托盘图标上显示未读告警数，为 0 时清除（macOS 不显示托盘图标，直接忽略）
+/// Windows 的托盘不显示标题，有未读告警时同时换成带红点的图标
+pub fn set_tray_badge(app: &AppHandle, count: usize) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let (title, tooltip) = if count > 0 {
+        (Some(count.to_string()), format!("Nanoboard - {} 条未读告警", count))
+    } else {
+        (None, "Nanoboard".to_string())
+    };
+
+    let _ = tray.set_title(title);
+    let _ = tray.set_tooltip(Some(tooltip));
+
+    // 只在有无未读告警切换时更换图标
+    let badged = count > 0;
+    if TRAY_BADGED.swap(badged, Ordering::Relaxed) != badged {
+        let icon = if badged { badged_tray_icon(app) } else { Some(tray_icon()) };
+        if let Some(icon) = icon {
+            if let Err(e) = tray.set_icon(Some(icon)) {
+                log::warn!("更新托盘图标失败: {}", e);
+            }
+        }
+    }
+}
+
 /// 构建系统托盘菜单 (仅 Windows/Linux)
 #[cfg(not(target_os = "macos"))]
 pub fn build_tray_menu(app: &AppHandle) -> Menu<Wry> {
@@ -85,16 +150,12 @@ pub fn build_tray_menu(app: &AppHandle) -> Menu<Wry> {
 pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     let tray_menu = build_tray_menu(app);
 
-    // 使用专用的托盘图标 (22x22 适合 macOS 托盘)
-    let icon_bytes = include_bytes!("../icons/tray_icon.png");
-    let icon = tauri::image::Image::new(icon_bytes, 22, 22);
-
     log::info!("Creating tray icon...");
 
-    let tray_result = TrayIconBuilder::new()
+    let tray_result = TrayIconBuilder::with_id(TRAY_ID)
         .menu(&tray_menu)
         .tooltip("Nanoboard")
-        .icon(icon)
+        .icon(tray_icon())
         .build(app);
 
     match tray_result {
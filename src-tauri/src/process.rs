@@ -12,6 +12,7 @@ use tauri::State;
 use std::sync::Mutex;
 
 use crate::AppState;
+use crate::logrotate;
 
 // 进程检查缓存，避免频繁刷新进程列表
 struct ProcessCheckCache {
@@ -533,13 +534,6 @@ pub async fn start_nanobot(port: Option<u16>, state: State<'_, AppState>) -> Res
         }
     }
 
-    // 打开日志文件用于追加（如果不存在则创建）
-    let log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .map_err(|e| format!("无法打开日志文件: {}", e))?;
-
     // 记录启动前的日志文件大小，用于后续读取新产生的日志
     let log_size_before = log_path.metadata()
         .map(|m| m.len())
@@ -556,13 +550,32 @@ pub async fn start_nanobot(port: Option<u16>, state: State<'_, AppState>) -> Res
         vec!["gateway".to_string(), "--port".to_string(), port.to_string()]
     };
 
-    // 启动 nanobot gateway，直接将 stdout 和 stderr 都重定向到日志文件
+    let rotation = logrotate::load_settings();
+
+    // 管道模式：stdout 和 stderr 由 nanoboard 读取后按轮转设置写入日志
+    // 默认模式：直接将 stdout 和 stderr 都重定向到日志文件
+    let (stdout, stderr, rotating_log) = if rotation.enabled {
+        let log = logrotate::open_gateway_log(rotation)?;
+        (Stdio::piped(), Stdio::piped(), Some(log))
+    } else {
+        // 打开日志文件用于追加（如果不存在则创建）
+        let log_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(|e| format!("无法打开日志文件: {}", e))?;
+
+        let stdout = Stdio::from(log_file.try_clone().map_err(|e| format!("复制文件句柄失败: {}", e))?);
+        (stdout, Stdio::from(log_file), None)
+    };
+
+    // 启动 nanobot gateway
     let mut child = match apply_hidden_window(Command::new(&nanobot_cmd))
         .args(&start_args)
         .env("PYTHONUTF8", "1")
         .env("PYTHONIOENCODING", "utf-8")
-        .stdout(Stdio::from(log_file.try_clone().map_err(|e| format!("复制文件句柄失败: {}", e))?))
-        .stderr(Stdio::from(log_file))
+        .stdout(stdout)
+        .stderr(stderr)
         .spawn()
     {
         Ok(c) => c,
@@ -574,6 +587,15 @@ pub async fn start_nanobot(port: Option<u16>, state: State<'_, AppState>) -> Res
         }
     };
 
+    if let Some(log) = rotating_log {
+        if let Some(out) = child.stdout.take() {
+            logrotate::pump(out, "stdout", log.clone());
+        }
+        if let Some(err) = child.stderr.take() {
+            logrotate::pump(err, "stderr", log);
+        }
+    }
+
     // 获取进程ID
     let id = child.id();
     log::info!("Nanobot进程已启动 (PID: {})，等待初始化...", id);
@@ -2027,10 +2049,11 @@ pub async fn get_dashboard_data(
     let system_info_future = get_system_info_internal();
 
     // 获取配置
-    let config_result = crate::config::load_config_internal();
+    let config_result = crate::config::load_config_internal()
+        .map(|config| crate::secrets::mask_secrets(&config));
 
-    // 获取日志统计
-    let log_stats_result = crate::logger::get_log_statistics_internal();
+    // 获取日志统计（在阻塞线程中扫描日志）
+    let log_stats_future = crate::logger::get_log_statistics_internal();
 
     // 获取网络统计
     let network_stats = {
@@ -2039,7 +2062,8 @@ pub async fn get_dashboard_data(
     };
 
     // 等待所有异步操作完成
-    let (status, system_info) = tokio::join!(status_future, system_info_future);
+    let (status, system_info, log_stats_result) =
+        tokio::join!(status_future, system_info_future, log_stats_future);
 
     Ok(json!({
         "status": status?,
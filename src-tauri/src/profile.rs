@@ -0,0 +1,253 @@
+// 配置档案模块
+// 将不同用途的配置（工作、个人、测试等）保存为命名档案，支持一键切换
+
+use anyhow::{Context, Result};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::fs;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::config;
+use crate::fsutil;
+use crate::process;
+use crate::secrets;
+use crate::AppState;
+
+/// 配置档案信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub size: u64,
+    pub modified: u64,
+    /// 档案内容与当前配置一致
+    pub active: bool,
+}
+
+/// 获取配置档案目录（与 config_history 同级）
+fn get_profiles_dir() -> Result<PathBuf> {
+    let home = home_dir().context("无法找到用户主目录")?;
+    Ok(home.join(".nanobot").join("config_profiles"))
+}
+
+/// 验证档案名称，防止路径遍历
+fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("档案名称不能为空".to_string());
+    }
+
+    if name.len() > 64 {
+        return Err("档案名称不能超过 64 个字符".to_string());
+    }
+
+    if name.starts_with('.') {
+        return Err("档案名称不能以点开头".to_string());
+    }
+
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err("档案名称只能包含字母、数字、连字符、下划线和点".to_string());
+    }
+
+    Ok(())
+}
+
+/// 获取档案文件路径
+fn get_profile_path(name: &str) -> Result<PathBuf, String> {
+    validate_profile_name(name)?;
+    let dir = get_profiles_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// 读取档案内容
+fn read_profile(name: &str) -> Result<JsonValue, String> {
+    let path = get_profile_path(name)?;
+
+    if !path.exists() {
+        return Err(format!("配置档案 {} 不存在", name));
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("读取配置档案失败: {}", e))?;
+
+    let profile: JsonValue = serde_json::from_str(&content)
+        .map_err(|e| format!("解析配置档案失败: {}", e))?;
+
+    secrets::decrypt_secrets(&profile)
+        .map_err(|e| format!("解密配置档案失败: {:#}", e))
+}
+
+/// 从当前配置创建档案
+#[tauri::command]
+pub async fn create_config_profile(name: String, overwrite: Option<bool>) -> Result<ConfigProfile, String> {
+    let path = get_profile_path(&name)?;
+
+    if path.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("配置档案 {} 已存在", name));
+    }
+
+    // 档案中的密钥使用本地口令加密保存
+    let config = secrets::encrypt_secrets(&config::read_config_file()?)
+        .map_err(|e| format!("加密配置档案失败: {:#}", e))?;
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("序列化配置失败: {}", e))?;
+
+    fsutil::atomic_write(&path, &content)
+        .map_err(|e| format!("保存配置档案失败: {}", e))?;
+
+    Ok(ConfigProfile {
+        name,
+        size: content.len() as u64,
+        modified: chrono::Utc::now().timestamp() as u64,
+        active: true,
+    })
+}
+
+/// 列出所有配置档案
+#[tauri::command]
+pub async fn list_config_profiles() -> Result<Vec<ConfigProfile>, String> {
+    let dir = get_profiles_dir().map_err(|e| e.to_string())?;
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let current = config::read_config_file().ok();
+    let mut profiles = Vec::new();
+
+    let entries = fs::read_dir(&dir)
+        .map_err(|e| format!("读取档案目录失败: {}", e))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()) else {
+            continue;
+        };
+
+        if validate_profile_name(&name).is_err() {
+            continue;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        let modified = metadata.modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let active = match (&current, read_profile(&name)) {
+            (Some(current), Ok(profile)) => *current == profile,
+            _ => false,
+        };
+
+        profiles.push(ConfigProfile {
+            name,
+            size: metadata.len(),
+            modified,
+            active,
+        });
+    }
+
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(profiles)
+}
+
+/// 重命名配置档案
+#[tauri::command]
+pub async fn rename_config_profile(name: String, new_name: String) -> Result<(), String> {
+    let old_path = get_profile_path(&name)?;
+    let new_path = get_profile_path(&new_name)?;
+
+    if !old_path.exists() {
+        return Err(format!("配置档案 {} 不存在", name));
+    }
+
+    if new_path.exists() {
+        return Err(format!("配置档案 {} 已存在", new_name));
+    }
+
+    fs::rename(&old_path, &new_path)
+        .map_err(|e| format!("重命名配置档案失败: {}", e))
+}
+
+/// 删除配置档案
+#[tauri::command]
+pub async fn delete_config_profile(name: String) -> Result<(), String> {
+    let path = get_profile_path(&name)?;
+
+    if !path.exists() {
+        return Err(format!("配置档案 {} 不存在", name));
+    }
+
+    fs::remove_file(&path)
+        .map_err(|e| format!("删除配置档案失败: {}", e))
+}
+
+/// 比较配置档案
+/// 未指定 other 时与当前配置比较
+#[tauri::command]
+pub async fn diff_config_profile(name: String, other: Option<String>) -> Result<JsonValue, String> {
+    let profile = read_profile(&name)?;
+
+    let (base, base_label) = match other {
+        Some(other) => (read_profile(&other)?, other),
+        None => (config::read_config_file()?, "current".to_string()),
+    };
+
+    let mut changes = config::diff_config_values(&base, &profile);
+    secrets::mask_diff(&mut changes);
+
+    Ok(json!({
+        "from": base_label,
+        "to": name,
+        "identical": changes.is_empty(),
+        "changes": changes
+    }))
+}
+
+/// 激活配置档案
+/// restart_gateway 为 true 且 gateway 正在运行时，切换后自动重启
+#[tauri::command]
+pub async fn activate_config_profile(
+    name: String,
+    restart_gateway: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<JsonValue, String> {
+    let profile = read_profile(&name)?;
+    let validation = config::replace_config(&profile)?;
+
+    let mut restart = JsonValue::Null;
+
+    if restart_gateway.unwrap_or(false) {
+        let port = state
+            .nanobot_process
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|m| m.get_port());
+
+        let stopped = process::stop_nanobot(state.clone()).await?;
+
+        // 只有原本在运行的 gateway 才重新启动
+        if stopped["status"] == "stopped" {
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            restart = process::start_nanobot(port, state).await?;
+        }
+    }
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已切换到配置档案 {}", name),
+        "validation": validation,
+        "restart": restart
+    }))
+}
@@ -0,0 +1,1081 @@
+// LLM Provider 模块
+// 使用配置中的 apiKey/apiBase 向各 provider 发送最小的认证请求，检测连通性；
+// 并从 provider 拉取模型列表，与内置目录合并后缓存到本地
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config;
+use crate::fsutil;
+
+/// Provider 接口风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiStyle {
+    /// OpenAI 兼容接口（Bearer 认证，/models 与 /chat/completions）
+    OpenAi,
+    /// Anthropic 原生接口（x-api-key 认证）
+    Anthropic,
+}
+
+/// 内置 provider 信息
+pub struct ProviderSpec {
+    pub id: &'static str,
+    /// 默认 API 地址（配置未指定 apiBase 时使用）
+    pub api_base: &'static str,
+    pub style: ApiStyle,
+    /// 通过 OAuth 登录，配置中没有 apiKey
+    pub oauth: bool,
+    /// 内置的常用模型列表（离线时的兜底目录）
+    pub models: &'static [&'static str],
+}
+
+/// 已知 provider 列表，与前端 config/providers.ts 保持一致
+pub static PROVIDERS: &[ProviderSpec] = &[
+    ProviderSpec {
+        id: "openrouter",
+        api_base: "https://openrouter.ai/api/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["anthropic/claude-sonnet-4-5", "openai/gpt-4o", "google/gemini-pro-1.5"],
+    },
+    ProviderSpec {
+        id: "anthropic",
+        api_base: "https://api.anthropic.com/v1",
+        style: ApiStyle::Anthropic,
+        oauth: false,
+        models: &["claude-opus-4-5", "claude-sonnet-4-5", "claude-haiku-4-5"],
+    },
+    ProviderSpec {
+        id: "openai",
+        api_base: "https://api.openai.com/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["gpt-4o", "gpt-4-turbo", "gpt-4o-mini"],
+    },
+    ProviderSpec {
+        id: "deepseek",
+        api_base: "https://api.deepseek.com",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["deepseek-chat", "deepseek-reasoner", "deepseek-coder"],
+    },
+    ProviderSpec {
+        id: "groq",
+        api_base: "https://api.groq.com/openai/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["llama-3.3-70b-versatile", "mixtral-8x7b-32768", "gemma2-9b-it"],
+    },
+    ProviderSpec {
+        id: "gemini",
+        api_base: "https://generativelanguage.googleapis.com/v1beta/openai",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["gemini-2.0-flash-exp", "gemini-pro", "gemini-1.5-pro"],
+    },
+    ProviderSpec {
+        id: "minimax",
+        api_base: "https://api.minimax.chat/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["MiniMax-Text-01", "abab6.5s-chat", "abab6.5-chat"],
+    },
+    ProviderSpec {
+        id: "aihubmix",
+        api_base: "https://aihubmix.com/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["anthropic/claude-sonnet-4-5", "openai/gpt-4o"],
+    },
+    ProviderSpec {
+        id: "dashscope",
+        api_base: "https://dashscope.aliyuncs.com/compatible-mode/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["qwen-turbo", "qwen-plus", "qwen-max"],
+    },
+    ProviderSpec {
+        id: "moonshot",
+        api_base: "https://api.moonshot.cn/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["moonshot-v1-8k", "moonshot-v1-32k", "moonshot-v1-128k"],
+    },
+    ProviderSpec {
+        id: "zhipu",
+        api_base: "https://open.bigmodel.cn/api/paas/v4",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["glm-4-flash", "glm-4-plus", "glm-4-air"],
+    },
+    ProviderSpec {
+        id: "vllm",
+        api_base: "http://localhost:8000/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &[],
+    },
+    ProviderSpec {
+        id: "siliconflow",
+        api_base: "https://api.siliconflow.cn/v1",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["Qwen/Qwen2.5-72B-Instruct", "Qwen/Qwen2.5-32B-Instruct", "deepseek-ai/DeepSeek-V3", "deepseek-ai/DeepSeek-R1"],
+    },
+    ProviderSpec {
+        id: "github_copilot",
+        api_base: "https://api.githubcopilot.com",
+        style: ApiStyle::OpenAi,
+        oauth: true,
+        models: &["gpt-4o", "gpt-4-turbo", "claude-3.5-sonnet"],
+    },
+    ProviderSpec {
+        id: "openai_codex",
+        api_base: "https://api.openai.com/v1",
+        style: ApiStyle::OpenAi,
+        oauth: true,
+        models: &["codex"],
+    },
+    ProviderSpec {
+        id: "volcengine",
+        api_base: "https://ark.cn-beijing.volces.com/api/v3",
+        style: ApiStyle::OpenAi,
+        oauth: false,
+        models: &["doubao-pro-32k", "doubao-pro-128k", "doubao-lite-32k", "doubao-lite-128k"],
+    },
+];
+
+/// 查找内置 provider 信息
+pub fn find_provider_spec(id: &str) -> Option<&'static ProviderSpec> {
+    PROVIDERS.iter().find(|p| p.id == id)
+}
+
+/// 配置中的 provider 连接信息
+#[derive(Debug, Clone)]
+pub struct ProviderEndpoint {
+    pub id: String,
+    pub api_base: String,
+    pub api_key: String,
+    pub style: ApiStyle,
+    pub extra_headers: Vec<(String, String)>,
+}
+
+/// 从配置中提取已配置的 provider（有 apiKey 或 apiBase 的条目）
+pub fn configured_providers(config: &JsonValue) -> Vec<ProviderEndpoint> {
+    let Some(providers) = config.get("providers").and_then(|p| p.as_object()) else {
+        return Vec::new();
+    };
+
+    let mut endpoints = Vec::new();
+
+    for (id, provider) in providers {
+        let spec = find_provider_spec(id);
+
+        if spec.map(|s| s.oauth).unwrap_or(false) {
+            continue;
+        }
+
+        let api_key = provider.get("apiKey").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+        let api_base = provider.get("apiBase").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+
+        if api_key.is_empty() && api_base.is_empty() {
+            continue;
+        }
+
+        let api_base = if api_base.is_empty() {
+            match spec {
+                Some(spec) => spec.api_base.to_string(),
+                None => continue,
+            }
+        } else {
+            api_base
+        };
+
+        let extra_headers = provider
+            .get("extraHeaders")
+            .and_then(|h| h.as_object())
+            .map(|h| {
+                h.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        endpoints.push(ProviderEndpoint {
+            id: id.clone(),
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key,
+            style: spec.map(|s| s.style).unwrap_or(ApiStyle::OpenAi),
+            extra_headers,
+        });
+    }
+
+    endpoints
+}
+
+/// 连通性测试错误分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorKind {
+    Auth,
+    Quota,
+    Dns,
+    Tls,
+    Timeout,
+    Connection,
+    NotFound,
+    Server,
+    Http,
+}
+
+/// 单个 provider 的测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderTestResult {
+    pub provider: String,
+    pub endpoint: String,
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error_kind: Option<ProviderErrorKind>,
+    pub message: String,
+}
+
+/// 根据传输层错误判断类别
+pub fn classify_request_error(error: &reqwest::Error) -> ProviderErrorKind {
+    if error.is_timeout() {
+        return ProviderErrorKind::Timeout;
+    }
+
+    // reqwest 不直接暴露 DNS/TLS 错误类型，检查完整的错误链
+    let mut chain = String::new();
+    let mut source: Option<&dyn std::error::Error> = Some(error);
+    while let Some(e) = source {
+        chain.push_str(&e.to_string().to_lowercase());
+        chain.push(' ');
+        source = e.source();
+    }
+
+    if chain.contains("dns") || chain.contains("lookup address") || chain.contains("name or service not known") {
+        ProviderErrorKind::Dns
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") || chain.contains("handshake") {
+        ProviderErrorKind::Tls
+    } else if chain.contains("timed out") {
+        ProviderErrorKind::Timeout
+    } else {
+        ProviderErrorKind::Connection
+    }
+}
+
+/// 表示额度或余额不足的错误码（OpenAI、DeepSeek、OpenRouter 等）
+const QUOTA_ERROR_CODES: &[&str] = &[
+    "insufficient_quota",
+    "insufficient_balance",
+    "insufficient_user_quota",
+    "billing_hard_limit_reached",
+    "quota_exceeded",
+    "credit_balance_too_low",
+];
+
+/// 根据 HTTP 状态码和响应内容判断类别
+/// 先看状态码，认证失败和服务端错误的响应里也可能出现 quota 等字样；其他状态码再检查响应中的错误码
+pub fn classify_http_status(status: u16, body: &str) -> ProviderErrorKind {
+    match status {
+        401 | 403 => return ProviderErrorKind::Auth,
+        500..=599 => return ProviderErrorKind::Server,
+        402 | 429 => return ProviderErrorKind::Quota,
+        _ => {}
+    }
+
+    let body = body.to_lowercase();
+    if QUOTA_ERROR_CODES.iter().any(|code| body.contains(code)) {
+        return ProviderErrorKind::Quota;
+    }
+
+    match status {
+        404 | 405 => ProviderErrorKind::NotFound,
+        _ => ProviderErrorKind::Http,
+    }
+}
+
+/// 构造带认证信息的请求
+fn authorized(request: reqwest::RequestBuilder, endpoint: &ProviderEndpoint) -> reqwest::RequestBuilder {
+    let mut request = match endpoint.style {
+        ApiStyle::OpenAi if !endpoint.api_key.is_empty() => request.bearer_auth(&endpoint.api_key),
+        ApiStyle::Anthropic => request
+            .header("x-api-key", &endpoint.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        _ => request,
+    };
+
+    for (name, value) in &endpoint.extra_headers {
+        request = request.header(name, value);
+    }
+
+    request.header("User-Agent", "Nanoboard/1.0")
+}
+
+/// 截取响应内容中的错误信息
+fn summarize_body(body: &str) -> String {
+    let message = serde_json::from_str::<JsonValue>(body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .or_else(|| v.get("message"))
+                .or_else(|| v.get("error"))
+                .and_then(|m| m.as_str().map(|s| s.to_string()))
+        })
+        .unwrap_or_else(|| body.trim().to_string());
+
+    message.chars().take(300).collect()
+}
+
+/// 发送请求并记录结果
+async fn send_probe(
+    provider: &str,
+    url: String,
+    request: reqwest::RequestBuilder,
+) -> ProviderTestResult {
+    let started = Instant::now();
+    let response = request.send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let success = response.status().is_success();
+            let body = response.text().await.unwrap_or_default();
+
+            ProviderTestResult {
+                provider: provider.to_string(),
+                endpoint: url,
+                ok: success,
+                status: Some(status),
+                latency_ms,
+                error_kind: (!success).then(|| classify_http_status(status, &body)),
+                message: if success { "连接成功".to_string() } else { summarize_body(&body) },
+            }
+        }
+        Err(e) => ProviderTestResult {
+            provider: provider.to_string(),
+            endpoint: url,
+            ok: false,
+            status: None,
+            latency_ms,
+            error_kind: Some(classify_request_error(&e)),
+            message: e.to_string(),
+        },
+    }
+}
+
+/// 测试单个 provider
+/// 先请求 /models；若该接口不存在，再用默认模型发送一个 max_tokens=1 的对话请求
+pub async fn test_provider(client: &reqwest::Client, endpoint: &ProviderEndpoint, model: Option<&str>) -> ProviderTestResult {
+    let models_url = format!("{}/models", endpoint.api_base);
+    let result = send_probe(
+        &endpoint.id,
+        models_url.clone(),
+        authorized(client.get(models_url), endpoint),
+    )
+    .await;
+
+    if result.error_kind != Some(ProviderErrorKind::NotFound) {
+        return result;
+    }
+
+    let Some(model) = model.filter(|m| !m.is_empty()) else {
+        return result;
+    };
+
+    let (chat_url, body) = match endpoint.style {
+        ApiStyle::OpenAi => (
+            format!("{}/chat/completions", endpoint.api_base),
+            json!({ "model": model, "max_tokens": 1, "messages": [{ "role": "user", "content": "ping" }] }),
+        ),
+        ApiStyle::Anthropic => (
+            format!("{}/messages", endpoint.api_base),
+            json!({ "model": model, "max_tokens": 1, "messages": [{ "role": "user", "content": "ping" }] }),
+        ),
+    };
+
+    send_probe(
+        &endpoint.id,
+        chat_url.clone(),
+        authorized(client.post(chat_url), endpoint).json(&body),
+    )
+    .await
+}
+
+/// 去掉模型名中的 provider 前缀（如 anthropic/claude-sonnet-4-5 → claude-sonnet-4-5）
+fn model_for_provider(model: &str, provider: &str) -> String {
+    model
+        .strip_prefix(provider)
+        .and_then(|m| m.strip_prefix('/'))
+        .unwrap_or(model)
+        .to_string()
+}
+
+/// 测试已配置 provider 的连通性
+/// provider 为空时测试所有已配置的 provider
+#[tauri::command]
+pub async fn test_provider_connectivity(
+    provider: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<Vec<ProviderTestResult>, String> {
+    let config = config::read_config_file()?;
+    let default_model = config
+        .pointer("/agents/defaults/model")
+        .and_then(|m| m.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let endpoints: Vec<ProviderEndpoint> = configured_providers(&config)
+        .into_iter()
+        .filter(|e| provider.as_ref().map(|p| &e.id == p).unwrap_or(true))
+        .collect();
+
+    if let Some(ref provider) = provider {
+        if endpoints.is_empty() {
+            return Err(format!("未找到已配置的 provider: {}", provider));
+        }
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs.unwrap_or(10)))
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for endpoint in endpoints {
+        let client = client.clone();
+        let model = model_for_provider(&default_model, &endpoint.id);
+        tasks.spawn(async move { test_provider(&client, &endpoint, Some(&model)).await });
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(r) => results.push(r),
+            Err(e) => log::warn!("provider 测试任务异常退出: {}", e),
+        }
+    }
+
+    results.sort_by(|a, b| a.provider.cmp(&b.provider));
+
+    Ok(results)
+}
+
+/// 模型目录缓存默认有效期（24 小时）
+const MODEL_CACHE_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// 单个 provider 的模型缓存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelCacheEntry {
+    api_base: String,
+    fetched_at: i64,
+    models: Vec<String>,
+}
+
+/// 模型目录缓存文件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ModelCache {
+    providers: HashMap<String, ModelCacheEntry>,
+}
+
+/// 获取模型目录缓存路径
+fn get_model_cache_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("无法找到用户主目录")?;
+    Ok(home.join(".nanobot").join("cache").join("models.json"))
+}
+
+/// 读取模型目录缓存，文件不存在或损坏时返回空缓存
+fn read_model_cache() -> ModelCache {
+    get_model_cache_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_model_cache(cache: &ModelCache) -> Result<(), String> {
+    let path = get_model_cache_path().map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(cache)
+        .map_err(|e| format!("序列化模型缓存失败: {}", e))?;
+
+    fsutil::atomic_write(&path, content)
+        .map_err(|e| format!("写入模型缓存失败: {}", e))?;
+
+    // 修改时间精度不足时也能读到新内容
+    *MODEL_CACHE_SNAPSHOT.lock().unwrap() = None;
+    Ok(())
+}
+
+/// 从 OpenAI 兼容的 /models 接口拉取模型 ID 列表
+pub async fn fetch_models(client: &reqwest::Client, endpoint: &ProviderEndpoint) -> Result<Vec<String>, String> {
+    let url = format!("{}/models", endpoint.api_base);
+    request_model_ids(authorized(client.get(&url), endpoint), &url).await
+}
+
+/// 发送模型列表请求并解析模型 ID
+async fn request_model_ids(request: reqwest::RequestBuilder, url: &str) -> Result<Vec<String>, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("请求 {} 失败: {}", url, e))?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+
+    if !status.is_success() {
+        return Err(format!("{} 返回 {}: {}", url, status.as_u16(), summarize_body(&body)));
+    }
+
+    let data: JsonValue = serde_json::from_str(&body)
+        .map_err(|e| format!("解析模型列表失败: {}", e))?;
+
+    // OpenAI 风格为 { data: [{ id }] }，Ollama 等返回 { models: [{ name }] }
+    let items = data
+        .get("data")
+        .or_else(|| data.get("models"))
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| "响应中没有模型列表".to_string())?;
+
+    let mut models: Vec<String> = items
+        .iter()
+        .filter_map(|item| {
+            item.get("id")
+                .or_else(|| item.get("name"))
+                .and_then(|v| v.as_str())
+                .or_else(|| item.as_str())
+                .map(|s| s.to_string())
+        })
+        .collect();
+
+    models.sort();
+    models.dedup();
+
+    Ok(models)
+}
+
+/// 单个 provider 的模型列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderModels {
+    pub provider: String,
+    /// live：刚从接口拉取；cache：来自本地缓存；static：仅内置目录
+    pub source: String,
+    pub fetched_at: Option<i64>,
+    pub models: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// 合并模型列表并去重
+fn merge_models(fetched: &[String], bundled: &[&str]) -> Vec<String> {
+    let mut models: Vec<String> = fetched.to_vec();
+    models.extend(bundled.iter().map(|m| m.to_string()));
+    models.sort();
+    models.dedup();
+    models
+}
+
+/// 按缓存文件修改时间记住的模型目录缓存，避免每次验证配置都读取文件
+static MODEL_CACHE_SNAPSHOT: Mutex<Option<(Option<SystemTime>, Arc<ModelCache>)>> = Mutex::new(None);
+
+/// 读取模型目录缓存，文件未变化时复用上次的结果
+fn cached_model_cache() -> Arc<ModelCache> {
+    let modified = get_model_cache_path()
+        .ok()
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok());
+
+    let mut snapshot = MODEL_CACHE_SNAPSHOT.lock().unwrap();
+    match snapshot.as_ref() {
+        Some((cached, cache)) if *cached == modified => cache.clone(),
+        _ => {
+            let cache = Arc::new(read_model_cache());
+            *snapshot = Some((modified, cache.clone()));
+            cache
+        }
+    }
+}
+
+/// 检查默认模型是否不在任何已知的模型列表中，返回警告信息，不发起网络请求
+pub fn unknown_model_warning(model: &str) -> Option<String> {
+    unknown_model_in(model, &cached_model_cache())
+}
+
+/// 模型名带 provider 前缀（如 openrouter/anthropic/claude-sonnet-4.5）且该 provider 有拉取过的列表时，只和该列表比较；
+/// 否则和所有拉取到的列表及内置目录比较。内置目录并不完整，没有任何拉取到的列表时不做判断
+fn unknown_model_in(model: &str, cache: &ModelCache) -> Option<String> {
+    let prefixed = model.split_once('/');
+    let matches = |m: &str| m == model || prefixed.map(|(_, rest)| m == rest).unwrap_or(false);
+
+    if let Some((provider, _)) = prefixed {
+        if let Some(entry) = cache.providers.get(provider).filter(|e| !e.models.is_empty()) {
+            if entry.models.iter().any(|m| matches(m)) {
+                return None;
+            }
+            return Some(format!("默认模型 {} 不在 {} 的模型列表中，请确认名称是否正确", model, provider));
+        }
+    }
+
+    let mut fetched = cache.providers.values().filter(|e| !e.models.is_empty()).peekable();
+    fetched.peek()?;
+
+    let known = fetched
+        .flat_map(|e| e.models.iter().map(String::as_str))
+        .chain(PROVIDERS.iter().flat_map(|p| p.models.iter().copied()))
+        .any(matches);
+
+    (!known).then(|| format!("默认模型 {} 不在任何已知的模型列表中，请确认名称是否正确", model))
+}
+
+/// 获取模型目录
+/// 已配置的 provider 优先使用未过期的缓存，否则从 /models 拉取；拉取失败时回退到旧缓存和内置目录
+#[tauri::command]
+pub async fn get_model_catalog(refresh: Option<bool>, ttl_secs: Option<i64>) -> Result<JsonValue, String> {
+    let config = config::read_config_file().unwrap_or_else(|_| json!({}));
+    let refresh = refresh.unwrap_or(false);
+    let ttl = ttl_secs.unwrap_or(MODEL_CACHE_TTL_SECS);
+    let now = chrono::Utc::now().timestamp();
+
+    let mut cache = read_model_cache();
+    let endpoints = configured_providers(&config);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+
+    // 需要重新拉取的 provider：强制刷新、缓存过期或 apiBase 已变更
+    let mut tasks = tokio::task::JoinSet::new();
+    for endpoint in &endpoints {
+        let fresh = cache
+            .providers
+            .get(&endpoint.id)
+            .map(|c| c.api_base == endpoint.api_base && now - c.fetched_at < ttl)
+            .unwrap_or(false);
+
+        if refresh || !fresh {
+            let client = client.clone();
+            let endpoint = endpoint.clone();
+            tasks.spawn(async move {
+                let result = fetch_models(&client, &endpoint).await;
+                (endpoint, result)
+            });
+        }
+    }
+
+    let mut errors: HashMap<String, String> = HashMap::new();
+    let mut live: HashSet<String> = HashSet::new();
+
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((endpoint, result)) = joined else { continue };
+
+        match result {
+            Ok(models) => {
+                live.insert(endpoint.id.clone());
+                cache.providers.insert(endpoint.id.clone(), ModelCacheEntry {
+                    api_base: endpoint.api_base.clone(),
+                    fetched_at: now,
+                    models,
+                });
+            }
+            Err(e) => {
+                errors.insert(endpoint.id.clone(), e);
+            }
+        }
+    }
+
+    if !live.is_empty() {
+        if let Err(e) = write_model_cache(&cache) {
+            log::warn!("{}", e);
+        }
+    }
+
+    let mut providers = Vec::new();
+    let configured: HashSet<&str> = endpoints.iter().map(|e| e.id.as_str()).collect();
+
+    for endpoint in &endpoints {
+        let bundled = find_provider_spec(&endpoint.id).map(|s| s.models).unwrap_or(&[]);
+        let cached = cache.providers.get(&endpoint.id);
+
+        let source = if live.contains(&endpoint.id) {
+            "live"
+        } else if cached.is_some() {
+            "cache"
+        } else {
+            "static"
+        };
+
+        providers.push(ProviderModels {
+            provider: endpoint.id.clone(),
+            source: source.to_string(),
+            fetched_at: cached.map(|c| c.fetched_at),
+            models: merge_models(cached.map(|c| c.models.as_slice()).unwrap_or(&[]), bundled),
+            error: errors.remove(&endpoint.id),
+        });
+    }
+
+    // 未配置的 provider 只返回内置目录
+    for spec in PROVIDERS.iter().filter(|p| !configured.contains(p.id) && !p.models.is_empty()) {
+        providers.push(ProviderModels {
+            provider: spec.id.to_string(),
+            source: "static".to_string(),
+            fetched_at: None,
+            models: merge_models(&[], spec.models),
+            error: None,
+        });
+    }
+
+    // 按模型汇总提供它的 provider
+    let mut by_model: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in &providers {
+        for model in &entry.models {
+            by_model.entry(model.clone()).or_default().push(entry.provider.clone());
+        }
+    }
+
+    let models: Vec<JsonValue> = by_model
+        .into_iter()
+        .map(|(id, providers)| json!({ "id": id, "providers": providers }))
+        .collect();
+
+    Ok(json!({
+        "providers": providers,
+        "models": models
+    }))
+}
+
+/// 本地 LLM 服务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalServerKind {
+    /// Ollama 原生接口（/api/tags），同时提供 /v1 兼容接口
+    Ollama,
+    /// OpenAI 兼容接口（/v1/models）
+    OpenaiCompatible,
+}
+
+/// 本地服务探测项
+struct LocalServerProbe {
+    name: &'static str,
+    port: u16,
+    kind: LocalServerKind,
+}
+
+/// 常见本地 LLM 服务的默认端口
+static LOCAL_SERVER_PROBES: &[LocalServerProbe] = &[
+    LocalServerProbe { name: "Ollama", port: 11434, kind: LocalServerKind::Ollama },
+    LocalServerProbe { name: "vLLM", port: 8000, kind: LocalServerKind::OpenaiCompatible },
+    LocalServerProbe { name: "LM Studio", port: 1234, kind: LocalServerKind::OpenaiCompatible },
+    LocalServerProbe { name: "llama.cpp / LocalAI", port: 8080, kind: LocalServerKind::OpenaiCompatible },
+    LocalServerProbe { name: "text-generation-webui", port: 5000, kind: LocalServerKind::OpenaiCompatible },
+    LocalServerProbe { name: "Jan", port: 1337, kind: LocalServerKind::OpenaiCompatible },
+    LocalServerProbe { name: "SGLang", port: 30000, kind: LocalServerKind::OpenaiCompatible },
+];
+
+/// 发现的本地 LLM 服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLlmServer {
+    pub name: String,
+    pub kind: LocalServerKind,
+    pub port: u16,
+    /// 可直接写入 provider 配置的 OpenAI 兼容地址
+    pub api_base: String,
+    pub models: Vec<String>,
+    pub latency_ms: u64,
+}
+
+/// 探测单个端口
+async fn probe_local_server(
+    client: &reqwest::Client,
+    host: &str,
+    name: &str,
+    port: u16,
+    kind: LocalServerKind,
+) -> Option<LocalLlmServer> {
+    let root = format!("http://{}:{}", host, port);
+    let url = match kind {
+        LocalServerKind::Ollama => format!("{}/api/tags", root),
+        LocalServerKind::OpenaiCompatible => format!("{}/v1/models", root),
+    };
+
+    let started = Instant::now();
+    let models = request_model_ids(client.get(&url), &url).await.ok()?;
+
+    Some(LocalLlmServer {
+        name: name.to_string(),
+        kind,
+        port,
+        api_base: format!("{}/v1", root),
+        models,
+        latency_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// 探测本机运行的 LLM 服务（Ollama、vLLM、LM Studio 等）
+/// extra_ports 中的端口会同时尝试 Ollama 和 OpenAI 兼容两种接口
+#[tauri::command]
+pub async fn discover_local_llm_servers(
+    extra_ports: Option<Vec<u16>>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<LocalLlmServer>, String> {
+    const HOST: &str = "127.0.0.1";
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(timeout_ms.unwrap_or(1500)))
+        .no_proxy()
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+
+    let mut probes: Vec<(String, u16, LocalServerKind)> = LOCAL_SERVER_PROBES
+        .iter()
+        .map(|p| (p.name.to_string(), p.port, p.kind))
+        .collect();
+
+    for port in extra_ports.unwrap_or_default() {
+        if probes.iter().any(|(_, p, _)| *p == port) {
+            continue;
+        }
+        probes.push((format!("localhost:{}", port), port, LocalServerKind::Ollama));
+        probes.push((format!("localhost:{}", port), port, LocalServerKind::OpenaiCompatible));
+    }
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (name, port, kind) in probes {
+        let client = client.clone();
+        tasks.spawn(async move { probe_local_server(&client, HOST, &name, port, kind).await });
+    }
+
+    let mut servers: Vec<LocalLlmServer> = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(Some(server)) = joined {
+            // 同一端口两种接口都响应时（如 Ollama），只保留一条
+            match servers.iter_mut().find(|s| s.port == server.port) {
+                Some(existing) if existing.kind == LocalServerKind::OpenaiCompatible => *existing = server,
+                Some(_) => {}
+                None => servers.push(server),
+            }
+        }
+    }
+
+    servers.sort_by_key(|s| s.port);
+
+    Ok(servers)
+}
+
+/// 将发现的本地服务写入 provider 配置（经过校验与历史备份）
+/// provider 默认为 vllm（nanobot 中用于任意 OpenAI 兼容的本地服务）
+#[tauri::command]
+pub async fn configure_local_provider(
+    api_base: String,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<JsonValue, String> {
+    let api_base = api_base.trim().trim_end_matches('/').to_string();
+    if !api_base.starts_with("http://") && !api_base.starts_with("https://") {
+        return Err(format!("无效的 API 地址: {}", api_base));
+    }
+
+    let provider = provider
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "vllm".to_string());
+
+    let pointer = format!("/providers/{}", provider.replace('~', "~0").replace('/', "~1"));
+    let model = model.filter(|m| !m.trim().is_empty());
+
+    let validation = config::update_config(|config| {
+        let mut block = config
+            .pointer(&pointer)
+            .cloned()
+            .filter(|b| b.is_object())
+            .unwrap_or_else(|| json!({}));
+
+        block["apiBase"] = json!(api_base);
+
+        // 本地服务通常不校验密钥，但 nanobot 要求 apiKey 非空
+        if block.get("apiKey").and_then(|k| k.as_str()).map(|k| k.is_empty()).unwrap_or(true) {
+            block["apiKey"] = json!("dummy");
+        }
+
+        config::set_value_at_pointer(config, &pointer, block)?;
+
+        if let Some(ref model) = model {
+            config::set_value_at_pointer(config, "/agents/defaults/model", json!(model))?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(json!({
+        "success": true,
+        "message": format!("已将 {} 写入 provider {}", api_base, provider),
+        "provider": provider,
+        "validation": validation
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 按顺序返回预设响应的本地 HTTP 服务，返回其地址
+    async fn mock_server(responses: Vec<(u16, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let _ = socket.read(&mut buf).await;
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn endpoint(api_base: &str) -> ProviderEndpoint {
+        ProviderEndpoint {
+            id: "mock".to_string(),
+            api_base: api_base.to_string(),
+            api_key: "sk-test".to_string(),
+            style: ApiStyle::OpenAi,
+            extra_headers: Vec::new(),
+        }
+    }
+
+    fn model_cache(entries: &[(&str, &[&str])]) -> ModelCache {
+        ModelCache {
+            providers: entries
+                .iter()
+                .map(|(id, models)| {
+                    let entry = ModelCacheEntry {
+                        models: models.iter().map(|m| m.to_string()).collect(),
+                        ..Default::default()
+                    };
+                    (id.to_string(), entry)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn prefixed_model_checked_against_its_provider() {
+        let cache = model_cache(&[("vllm", &["my-model", "gpt-4o"])]);
+
+        assert_eq!(unknown_model_in("vllm/my-model", &cache), None);
+        assert_eq!(unknown_model_in("vllm/gpt-4o", &cache), None);
+
+        let warning = unknown_model_in("vllm/gtp-4o", &cache).unwrap();
+        assert!(warning.contains("vllm/gtp-4o") && warning.contains("vllm 的模型列表"), "{}", warning);
+    }
+
+    #[test]
+    fn unprefixed_model_checked_against_all_lists() {
+        let cache = model_cache(&[("vllm", &["my-model"]), ("deepseek", &["deepseek-chat"])]);
+
+        assert_eq!(unknown_model_in("deepseek-chat", &cache), None);
+        // 内置目录中的模型
+        assert_eq!(unknown_model_in("claude-sonnet-4-5", &cache), None);
+        // 前缀不是有列表的 provider 时按完整名称或去掉前缀后比较
+        assert_eq!(unknown_model_in("anthropic/claude-sonnet-4-5", &cache), None);
+
+        for typo in ["gpt-4oo", "claude-sonet-4", "anthropic/claude-sonet-4"] {
+            let warning = unknown_model_in(typo, &cache).unwrap();
+            assert!(warning.contains("任何已知的模型列表"), "{}", warning);
+        }
+    }
+
+    #[test]
+    fn no_warning_without_fetched_lists() {
+        assert_eq!(unknown_model_in("gpt-4oo", &ModelCache::default()), None);
+        assert_eq!(unknown_model_in("vllm/gtp-4o", &model_cache(&[("vllm", &[])])), None);
+    }
+
+    #[test]
+    fn status_takes_precedence_over_body() {
+        let quota_body = r#"{"error":{"message":"You exceeded your current quota","code":"insufficient_quota"}}"#;
+
+        assert_eq!(classify_http_status(401, quota_body), ProviderErrorKind::Auth);
+        assert_eq!(classify_http_status(403, "balance"), ProviderErrorKind::Auth);
+        assert_eq!(classify_http_status(503, quota_body), ProviderErrorKind::Server);
+        assert_eq!(classify_http_status(429, "{}"), ProviderErrorKind::Quota);
+        assert_eq!(classify_http_status(402, ""), ProviderErrorKind::Quota);
+    }
+
+    #[test]
+    fn body_codes_only_match_specific_identifiers() {
+        assert_eq!(
+            classify_http_status(400, r#"{"error":{"code":"insufficient_quota"}}"#),
+            ProviderErrorKind::Quota
+        );
+        assert_eq!(
+            classify_http_status(400, r#"{"error":{"message":"invalid field: balance_quota"}}"#),
+            ProviderErrorKind::Http
+        );
+        assert_eq!(classify_http_status(404, "model not found"), ProviderErrorKind::NotFound);
+        assert_eq!(classify_http_status(418, ""), ProviderErrorKind::Http);
+    }
+
+    #[tokio::test]
+    async fn probe_classifies_mock_responses() {
+        let client = reqwest::Client::new();
+        let base = mock_server(vec![
+            (401, r#"{"error":{"message":"Incorrect API key","code":"invalid_api_key"}}"#),
+            (500, r#"{"error":{"message":"quota service unavailable"}}"#),
+            (404, "not found"),
+            (400, r#"{"error":{"message":"balance too low","code":"insufficient_balance"}}"#),
+            (200, r#"{"data":[{"id":"gpt-4o"}]}"#),
+        ])
+        .await;
+
+        let result = test_provider(&client, &endpoint(&base), None).await;
+        assert_eq!(result.error_kind, Some(ProviderErrorKind::Auth));
+        assert_eq!(result.status, Some(401));
+        assert_eq!(result.message, "Incorrect API key");
+
+        let result = test_provider(&client, &endpoint(&base), None).await;
+        assert_eq!(result.error_kind, Some(ProviderErrorKind::Server));
+
+        // /models 不存在时改用对话接口
+        let result = test_provider(&client, &endpoint(&base), Some("gpt-4o")).await;
+        assert_eq!(result.error_kind, Some(ProviderErrorKind::Quota));
+        assert!(result.endpoint.ends_with("/chat/completions"));
+
+        let result = test_provider(&client, &endpoint(&base), None).await;
+        assert!(result.ok);
+        assert_eq!(result.error_kind, None);
+    }
+
+    #[tokio::test]
+    async fn probe_reports_connection_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let result = test_provider(&reqwest::Client::new(), &endpoint(&base), None).await;
+        assert!(!result.ok);
+        assert_eq!(result.status, None);
+        assert_eq!(result.error_kind, Some(ProviderErrorKind::Connection));
+    }
+}
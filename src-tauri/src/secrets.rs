@@ -0,0 +1,473 @@
+// 敏感信息模块
+// 按键名识别配置中的密钥（apiKey、token、secret 等），负责返回前端时打码、
+// 以及写入历史快照/档案时使用本地口令加密
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::config;
+use crate::fsutil::{self, WriteOptions};
+
+/// 打码后的占位前缀
+pub const MASK: &str = "********";
+
+/// 导出时替换密钥的占位符
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 加密值前缀，格式为 enc:v1:<base64(nonce || ciphertext)>
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// 敏感键名后缀（忽略大小写、下划线和连字符）
+/// 使用后缀匹配，避免 maxTokens 之类的字段被误判
+const SECRET_KEY_SUFFIXES: &[&str] = &[
+    "apikey",
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "accesskey",
+    "privatekey",
+    "encryptkey",
+    "authorization",
+];
+
+/// 判断键名是否为敏感字段
+pub fn is_secret_key(key: &str) -> bool {
+    let normalized: String = key
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    SECRET_KEY_SUFFIXES.iter().any(|suffix| normalized.ends_with(suffix))
+}
+
+/// 判断 JSON Pointer 是否指向敏感字段
+pub fn is_secret_pointer(pointer: &str) -> bool {
+    pointer
+        .rsplit('/')
+        .next()
+        .map(|last| is_secret_key(&last.replace("~1", "/").replace("~0", "~")))
+        .unwrap_or(false)
+}
+
+/// 对单个密钥打码，保留末尾 4 个字符便于辨认
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+
+    if chars.is_empty() {
+        return String::new();
+    }
+
+    if chars.len() <= 8 {
+        return MASK.to_string();
+    }
+
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", MASK, tail)
+}
+
+/// 遍历所有字符串类型的敏感字段，回调参数为 (JSON Pointer, 值)
+fn for_each_secret_mut(value: &mut JsonValue, path: &mut String, f: &mut dyn FnMut(&str, &mut JsonValue)) {
+    match value {
+        JsonValue::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+
+                if is_secret_key(key) && child.is_string() {
+                    f(path, child);
+                } else {
+                    for_each_secret_mut(child, path, f);
+                }
+
+                path.truncate(len);
+            }
+        }
+        JsonValue::Array(arr) => {
+            for (index, child) in arr.iter_mut().enumerate() {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&index.to_string());
+                for_each_secret_mut(child, path, f);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 返回打码后的配置副本
+pub fn mask_secrets(config: &JsonValue) -> JsonValue {
+    let mut masked = config.clone();
+    for_each_secret_mut(&mut masked, &mut String::new(), &mut |_, v| {
+        if let Some(s) = v.as_str() {
+            *v = JsonValue::String(mask_secret(s));
+        }
+    });
+    masked
+}
+
+/// 返回密钥被完全替换为占位符的配置副本
+pub fn redact_secrets(config: &JsonValue) -> JsonValue {
+    let mut redacted = config.clone();
+    for_each_secret_mut(&mut redacted, &mut String::new(), &mut |_, v| {
+        if v.as_str().map(|s| !s.is_empty()).unwrap_or(false) {
+            *v = JsonValue::String(REDACTED.to_string());
+        }
+    });
+    redacted
+}
+
+/// 还原前端回传的打码值
+/// 若某个敏感字段的值仍等于当前配置对应值的打码结果（或导出时的占位符），
+/// 说明用户没有修改，恢复为原值
+pub fn unmask_secrets(config: &mut JsonValue, current: &JsonValue) {
+    for_each_secret_mut(config, &mut String::new(), &mut |path, v| {
+        let Some(original) = current.pointer(path).and_then(|o| o.as_str()) else {
+            return;
+        };
+
+        if v.as_str() == Some(mask_secret(original).as_str()) || v.as_str() == Some(REDACTED) {
+            *v = JsonValue::String(original.to_string());
+        }
+    });
+}
+
+/// 收集仍为导出占位符的敏感字段路径（JSON Pointer）
+pub fn redacted_placeholders(config: &JsonValue) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut config = config.clone();
+    for_each_secret_mut(&mut config, &mut String::new(), &mut |path, v| {
+        if v.as_str() == Some(REDACTED) {
+            paths.push(path.to_string());
+        }
+    });
+    paths
+}
+
+/// 收集配置中所有非空的密钥明文
+pub fn secret_values(config: &JsonValue) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut config = config.clone();
+    for_each_secret_mut(&mut config, &mut String::new(), &mut |_, v| {
+        if let Some(s) = v.as_str() {
+            if !s.is_empty() && !s.starts_with(ENCRYPTED_PREFIX) {
+                values.push(s.to_string());
+            }
+        }
+    });
+    values
+}
+
+/// 对差异列表中的密钥打码（diff_config_values 的输出）
+pub fn mask_diff(changes: &mut [JsonValue]) {
+    for change in changes.iter_mut() {
+        let secret = change["path"].as_str().map(is_secret_pointer).unwrap_or(false);
+
+        for side in ["old", "new"] {
+            let value = &mut change[side];
+            *value = match value.as_str() {
+                Some(s) if secret => JsonValue::String(mask_secret(s)),
+                _ => mask_secrets(value),
+            };
+        }
+    }
+}
+
+/// 获取本地口令文件路径
+fn get_key_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("无法找到用户主目录")?;
+    Ok(home.join(".nanobot").join("nanoboard.key"))
+}
+
+/// 加载本地口令，首次使用时随机生成并以 0600 权限保存
+/// 文件只在不存在时创建，与其他进程同时创建时使用先写入的口令，已有的口令不会被覆盖
+fn load_or_create_key_at(path: &Path) -> Result<Key<Aes256Gcm>> {
+    if !path.exists() {
+        let passphrase = BASE64.encode(Aes256Gcm::generate_key(OsRng));
+        fsutil::create_new_with(path, &passphrase, WriteOptions { private: true, ..Default::default() })
+            .context("保存本地口令失败")?;
+    }
+
+    // 总是读回文件中的口令，确保使用的密钥与磁盘上的一致
+    let passphrase = fs::read_to_string(path)
+        .context("读取本地口令失败")?
+        .trim()
+        .to_string();
+
+    if passphrase.is_empty() {
+        return Err(anyhow!("本地口令文件为空: {:?}", path));
+    }
+
+    let digest = Sha256::digest(passphrase.as_bytes());
+    Ok(*Key::<Aes256Gcm>::from_slice(&digest))
+}
+
+fn cipher() -> Result<&'static Aes256Gcm> {
+    static CIPHER: OnceLock<Aes256Gcm> = OnceLock::new();
+    // 串行化首次加载，避免多个线程各自生成口令
+    static INIT: Mutex<()> = Mutex::new(());
+
+    if let Some(cipher) = CIPHER.get() {
+        return Ok(cipher);
+    }
+
+    let _guard = INIT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cipher) = CIPHER.get() {
+        return Ok(cipher);
+    }
+
+    let key = load_or_create_key_at(&get_key_path()?)?;
+    Ok(CIPHER.get_or_init(|| Aes256Gcm::new(&key)))
+}
+
+fn encrypt_value(cipher: &Aes256Gcm, plain: &str) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain.as_bytes())
+        .map_err(|_| anyhow!("加密失败"))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+}
+
+fn decrypt_value(cipher: &Aes256Gcm, encoded: &str) -> Result<String> {
+    let payload = BASE64
+        .decode(&encoded[ENCRYPTED_PREFIX.len()..])
+        .context("密文格式无效")?;
+
+    if payload.len() < 12 {
+        return Err(anyhow!("密文长度无效"));
+    }
+
+    let (nonce, ciphertext) = payload.split_at(12);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("解密失败，本地口令可能已更换"))?;
+
+    String::from_utf8(plain).context("解密结果不是有效的 UTF-8")
+}
+
+/// 返回密钥已加密的配置副本，用于历史快照和配置档案
+pub fn encrypt_secrets(config: &JsonValue) -> Result<JsonValue> {
+    encrypt_secrets_with(config, cipher)
+}
+
+/// 只有存在需要加密的值时才获取密钥
+fn encrypt_secrets_with<'a, F>(config: &JsonValue, cipher: F) -> Result<JsonValue>
+where
+    F: Fn() -> Result<&'a Aes256Gcm>,
+{
+    let mut encrypted = config.clone();
+    let mut error = None;
+
+    for_each_secret_mut(&mut encrypted, &mut String::new(), &mut |path, v| {
+        let Some(s) = v.as_str() else { return };
+        if s.is_empty() || s.starts_with(ENCRYPTED_PREFIX) || error.is_some() {
+            return;
+        }
+        match cipher().and_then(|c| encrypt_value(c, s)) {
+            Ok(enc) => *v = JsonValue::String(enc),
+            Err(e) => error = Some(e.context(format!("加密 {} 失败", path))),
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(encrypted),
+    }
+}
+
+/// 解密配置中的密钥，未加密的值原样保留（兼容旧快照）
+pub fn decrypt_secrets(config: &JsonValue) -> Result<JsonValue> {
+    decrypt_secrets_with(config, cipher)
+}
+
+/// 只有存在已加密的值时才获取密钥
+fn decrypt_secrets_with<'a, F>(config: &JsonValue, cipher: F) -> Result<JsonValue>
+where
+    F: Fn() -> Result<&'a Aes256Gcm>,
+{
+    let mut decrypted = config.clone();
+    let mut error = None;
+
+    for_each_secret_mut(&mut decrypted, &mut String::new(), &mut |path, v| {
+        let Some(s) = v.as_str() else { return };
+        if !s.starts_with(ENCRYPTED_PREFIX) || error.is_some() {
+            return;
+        }
+        match cipher().and_then(|c| decrypt_value(c, s)) {
+            Ok(plain) => *v = JsonValue::String(plain),
+            Err(e) => error = Some(e.context(format!("解密 {} 失败", path))),
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(decrypted),
+    }
+}
+
+/// 显示指定敏感字段的明文
+#[tauri::command]
+pub async fn reveal_config_secret(pointer: String) -> Result<String, String> {
+    if !is_secret_pointer(&pointer) {
+        return Err(format!("{} 不是敏感字段", pointer));
+    }
+
+    let config = config::read_config_file()?;
+
+    config
+        .pointer(&pointer)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("路径 {} 不存在", pointer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> JsonValue {
+        json!({
+            "providers": {
+                "openai": { "apiKey": "sk-abcdefghijkl", "apiBase": "https://api.openai.com/v1" },
+                "anthropic": { "apiKey": "" }
+            },
+            "agents": { "defaults": { "maxTokens": 8192 } },
+            "channels": { "telegram": { "token": "123:secret-token" } },
+            "tools": { "mcpServers": { "gh": { "env": { "GITHUB_TOKEN": "short" } } } }
+        })
+    }
+
+    #[test]
+    fn recognizes_secret_keys() {
+        for key in ["apiKey", "api_key", "botToken", "client-secret", "PASSWORD", "Authorization", "GITHUB_TOKEN"] {
+            assert!(is_secret_key(key), "{}", key);
+        }
+        for key in ["maxTokens", "tokenizer", "apiBase", "secretary"] {
+            assert!(!is_secret_key(key), "{}", key);
+        }
+        assert!(is_secret_pointer("/providers/openai/apiKey"));
+        assert!(is_secret_pointer("/tools/mcpServers/gh/headers/Authorization"));
+        assert!(!is_secret_pointer("/agents/defaults/maxTokens"));
+    }
+
+    #[test]
+    fn masks_all_but_last_four() {
+        assert_eq!(mask_secret(""), "");
+        assert_eq!(mask_secret("short"), MASK);
+        assert_eq!(mask_secret("12345678"), MASK);
+        assert_eq!(mask_secret("sk-abcdefghijkl"), format!("{}ijkl", MASK));
+        // 按字符而不是字节截取
+        assert_eq!(mask_secret("密钥密钥密钥密钥尾巴"), format!("{}密钥尾巴", MASK));
+    }
+
+    #[test]
+    fn unmask_restores_untouched_values() {
+        let current = sample();
+        let mut edited = mask_secrets(&current);
+
+        assert_eq!(edited["providers"]["openai"]["apiKey"], format!("{}ijkl", MASK));
+        assert_eq!(edited["tools"]["mcpServers"]["gh"]["env"]["GITHUB_TOKEN"], MASK);
+        assert_eq!(edited["agents"]["defaults"]["maxTokens"], 8192);
+
+        // 用户修改了 telegram token，其余保持打码值或导出占位符
+        edited["channels"]["telegram"]["token"] = json!("456:new-token");
+        edited["tools"]["mcpServers"]["gh"]["env"]["GITHUB_TOKEN"] = json!(REDACTED);
+        unmask_secrets(&mut edited, &current);
+
+        assert_eq!(edited["providers"]["openai"]["apiKey"], "sk-abcdefghijkl");
+        assert_eq!(edited["channels"]["telegram"]["token"], "456:new-token");
+        assert_eq!(edited["tools"]["mcpServers"]["gh"]["env"]["GITHUB_TOKEN"], "short");
+        assert_eq!(edited["providers"]["anthropic"]["apiKey"], "");
+    }
+
+    #[test]
+    fn unmask_keeps_placeholders_without_original() {
+        let mut imported = json!({ "providers": { "deepseek": { "apiKey": REDACTED } } });
+        unmask_secrets(&mut imported, &sample());
+
+        assert_eq!(redacted_placeholders(&imported), vec!["/providers/deepseek/apiKey".to_string()]);
+        assert!(redacted_placeholders(&redact_secrets(&json!({ "a": { "token": "" } }))).is_empty());
+    }
+
+    /// 测试用的临时目录，不影响真实的 ~/.nanobot，也不修改 HOME
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nanoboard-secrets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn key_is_created_once_and_never_overwritten() {
+        let dir = temp_dir("key");
+        let path = dir.join("nanoboard.key");
+
+        let key = load_or_create_key_at(&path).unwrap();
+        let passphrase = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(load_or_create_key_at(&path).unwrap(), key);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), passphrase);
+
+        // 已有文件不会被新口令覆盖
+        assert!(!fsutil::create_new_with(&path, "other", WriteOptions::default()).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), passphrase);
+
+        // 并发创建时所有调用者得到同一个密钥
+        let shared = dir.join("shared.key");
+        let keys: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| load_or_create_key_at(&shared).unwrap())).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(keys.iter().all(|k| *k == keys[0]));
+
+        std::fs::write(dir.join("empty.key"), "").unwrap();
+        assert!(load_or_create_key_at(&dir.join("empty.key")).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn encrypt_round_trip() {
+        let dir = temp_dir("round-trip");
+        let cipher = Aes256Gcm::new(&load_or_create_key_at(&dir.join("nanoboard.key")).unwrap());
+        let cipher = || Ok(&cipher);
+        let encrypt = |config: &JsonValue| encrypt_secrets_with(config, cipher);
+        let decrypt = |config: &JsonValue| decrypt_secrets_with(config, cipher);
+
+        let config = sample();
+        let encrypted = encrypt(&config).unwrap();
+
+        let api_key = encrypted["providers"]["openai"]["apiKey"].as_str().unwrap();
+        assert!(api_key.starts_with(ENCRYPTED_PREFIX));
+        assert_eq!(encrypted["providers"]["anthropic"]["apiKey"], "");
+        assert_eq!(encrypted["providers"]["openai"]["apiBase"], config["providers"]["openai"]["apiBase"]);
+        assert!(secret_values(&encrypted).is_empty());
+
+        // 已加密的值不会重复加密，每次加密使用不同的 nonce
+        assert_eq!(encrypt(&encrypted).unwrap(), encrypted);
+        assert_ne!(encrypt(&config).unwrap(), encrypted);
+
+        assert_eq!(decrypt(&encrypted).unwrap(), config);
+        assert_eq!(decrypt(&config).unwrap(), config);
+
+        let mut tampered = encrypted.clone();
+        tampered["providers"]["openai"]["apiKey"] = json!(format!("{}AAAA", ENCRYPTED_PREFIX));
+        assert!(decrypt(&tampered).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -4,6 +4,8 @@ use serde_json::json;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::fsutil;
+
 /// 获取workspace路径
 fn get_workspace_path() -> Result<PathBuf> {
     let home = home_dir().context("无法找到用户主目录")?;
@@ -339,7 +341,7 @@ pub async fn save_session_memory(session_id: String, content: String) -> Result<
     let file_path = memory_path.join(&session_id);
 
     // 写入文件
-    fs::write(&file_path, content)
+    fsutil::atomic_write(&file_path, content)
         .map_err(|e| format!("保存会话内容失败: {}", e))?;
 
     Ok(json!({
@@ -360,7 +362,7 @@ pub async fn save_workspace_file(file_name: String, content: String) -> Result<s
     let file_path = workspace_path.join(&file_name);
 
     // 写入文件
-    fs::write(&file_path, content)
+    fsutil::atomic_write(&file_path, content)
         .map_err(|e| format!("保存工作区文件失败: {}", e))?;
 
     Ok(json!({
@@ -739,7 +741,7 @@ pub async fn save_skill(skill_id: String, content: String) -> Result<serde_json:
 
     let skill_path = skills_path.join(&skill_file);
 
-    fs::write(&skill_path, &content)
+    fsutil::atomic_write(&skill_path, &content)
         .map_err(|e| format!("保存 Skill 失败: {}", e))?;
 
     Ok(json!({
@@ -1136,7 +1138,7 @@ pub async fn delete_file(relative_path: String) -> Result<serde_json::Value, Str
 
 /// 获取 chat sessions 路径
 /// 动态搜索 sessions 文件夹，优先使用 workspace/sessions，其次使用 .nanobot/sessions
-fn get_chat_sessions_path() -> Result<PathBuf> {
+pub(crate) fn get_chat_sessions_path() -> Result<PathBuf> {
     let home = home_dir().context("无法找到用户主目录")?;
     let nanobot_dir = home.join(".nanobot");
 
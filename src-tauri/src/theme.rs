@@ -6,6 +6,8 @@ use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::State;
 
+use crate::fsutil;
+
 /// 获取主题配置文件路径
 fn get_theme_config_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -44,7 +46,7 @@ fn save_theme_to_file(theme: &str) -> Result<(), String> {
     let content_str = serde_json::to_string_pretty(&content)
         .map_err(|e| format!("序列化主题失败: {}", e))?;
 
-    fs::write(&path, content_str)
+    fsutil::atomic_write(&path, content_str)
         .map_err(|e| format!("保存主题文件失败: {}", e))?;
 
     Ok(())
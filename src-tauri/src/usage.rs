@@ -0,0 +1,830 @@
+// 用量统计模块
+// 从 gateway 日志和会话文件中提取每次 LLM 请求的 token 数，没有记录时按消息文本估算；
+// 按模型、provider、渠道和日期汇总，并按可配置的价格表估算费用。
+// 同一请求可能同时出现在日志和会话文件中：会话中的回复与同一渠道、时间相近的日志记录视为同一请求，只使用日志中的数据
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::fsutil;
+use crate::logexport::csv_field;
+use crate::logfiles::{self, LogFile};
+use crate::logparse::LogRecord;
+use crate::provider;
+use crate::session;
+
+/// 未知的模型、provider 或渠道
+const UNKNOWN: &str = "unknown";
+
+/// 估算时计入上下文的最近消息数（与 nanobot 默认的 memory_window 一致）
+const CONTEXT_MESSAGES: usize = 50;
+
+/// 估算时每条消息的格式开销
+const MESSAGE_OVERHEAD: u64 = 4;
+
+/// 会话中的回复与日志中的 token 记录相差不超过这么多秒时视为同一请求
+const MATCH_WINDOW_SECS: i64 = 120;
+
+/// 用量来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageSource {
+    Log,
+    Session,
+}
+
+/// 一次 LLM 请求的用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEntry {
+    /// 日期（YYYY-MM-DD）
+    pub day: String,
+    pub timestamp: Option<String>,
+    pub model: String,
+    pub provider: String,
+    pub channel: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// token 数是按文本估算的
+    pub estimated: bool,
+    pub source: UsageSource,
+}
+
+/// 模型价格（每百万 token）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    /// 模型名，不区分大小写，支持 * 通配；带 provider 前缀的模型也会用去掉前缀后的名称匹配
+    pub model: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// 价格表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsagePriceTable {
+    /// 货币单位，仅用于显示
+    pub currency: String,
+    pub prices: Vec<ModelPrice>,
+}
+
+impl Default for UsagePriceTable {
+    /// 默认价格仅供参考，以 provider 官网为准
+    fn default() -> Self {
+        let price = |model: &str, input: f64, output: f64| ModelPrice {
+            model: model.to_string(),
+            input_per_million: input,
+            output_per_million: output,
+        };
+
+        Self {
+            currency: "USD".to_string(),
+            prices: vec![
+                price("gpt-4o", 2.5, 10.0),
+                price("gpt-4o-mini", 0.15, 0.6),
+                price("claude-opus-4-5*", 5.0, 25.0),
+                price("claude-sonnet-4*", 3.0, 15.0),
+                price("claude-haiku-4-5*", 1.0, 5.0),
+                price("deepseek-chat", 0.27, 1.1),
+                price("deepseek-reasoner", 0.55, 2.19),
+                price("gemini-2.0-flash*", 0.1, 0.4),
+            ],
+        }
+    }
+}
+
+impl UsagePriceTable {
+    fn validate(&self) -> Result<(), String> {
+        for price in &self.prices {
+            if price.model.trim().is_empty() {
+                return Err("模型名不能为空".to_string());
+            }
+            for value in [price.input_per_million, price.output_per_million] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(format!("模型 {} 的价格无效", price.model));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile(&self) -> PriceMatcher {
+        let rules = self
+            .prices
+            .iter()
+            .filter_map(|price| {
+                let pattern = format!("(?i)^{}$", regex::escape(price.model.trim()).replace(r"\*", ".*"));
+                Regex::new(&pattern).ok().map(|r| (r, price.model.trim().len(), price.clone()))
+            })
+            .collect();
+
+        PriceMatcher { rules }
+    }
+}
+
+/// 编译后的价格表
+struct PriceMatcher {
+    rules: Vec<(Regex, usize, ModelPrice)>,
+}
+
+impl PriceMatcher {
+    /// 查找模型价格，多条匹配时使用最长（最具体）的规则
+    fn find(&self, model: &str) -> Option<&ModelPrice> {
+        let bare = model.rsplit('/').next().unwrap_or(model);
+
+        self.rules
+            .iter()
+            .filter(|(regex, _, _)| regex.is_match(model) || regex.is_match(bare))
+            .max_by_key(|(_, len, _)| *len)
+            .map(|(_, _, price)| price)
+    }
+
+    fn cost(&self, entry: &UsageEntry) -> Option<f64> {
+        self.find(&entry.model).map(|price| {
+            (entry.prompt_tokens as f64 * price.input_per_million
+                + entry.completion_tokens as f64 * price.output_per_million)
+                / 1_000_000.0
+        })
+    }
+}
+
+/// 汇总维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageDimension {
+    Day,
+    Model,
+    Provider,
+    Channel,
+}
+
+/// 查询参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageQuery {
+    /// 起始日期（YYYY-MM-DD，包含）
+    pub since: Option<String>,
+    /// 截止日期（YYYY-MM-DD，包含）
+    pub until: Option<String>,
+    /// 汇总维度，默认按日期和模型
+    pub group_by: Option<Vec<UsageDimension>>,
+    /// 是否包含估算的用量，默认包含
+    pub include_estimates: Option<bool>,
+}
+
+/// 汇总行，未参与汇总的维度为空
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRow {
+    pub day: Option<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub channel: Option<String>,
+    pub requests: usize,
+    /// 其中按文本估算的请求数
+    pub estimated_requests: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// 估算费用（不含没有价格的模型）
+    pub cost: f64,
+    /// 价格表中找不到模型的请求数
+    pub unpriced_requests: usize,
+}
+
+impl UsageRow {
+    fn add(&mut self, entry: &UsageEntry, cost: Option<f64>) {
+        self.requests += 1;
+        self.prompt_tokens += entry.prompt_tokens;
+        self.completion_tokens += entry.completion_tokens;
+        self.total_tokens += entry.prompt_tokens + entry.completion_tokens;
+        if entry.estimated {
+            self.estimated_requests += 1;
+        }
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+/// 查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    /// 按日期从新到旧、同一天内按 token 数从多到少排列
+    pub rows: Vec<UsageRow>,
+    pub totals: UsageRow,
+    pub currency: String,
+    /// 价格表中找不到的模型
+    pub unpriced_models: Vec<String>,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageExportResult {
+    pub path: String,
+    pub rows: usize,
+    pub bytes: u64,
+}
+
+struct Patterns {
+    prompt: Regex,
+    completion: Regex,
+    model: Regex,
+    session: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| Patterns {
+        // "usage: {'prompt_tokens': 812, 'completion_tokens': 64}"、"input_tokens=812"
+        prompt: Regex::new(r#"(?i)\b(?:prompt|input)[_ ]tokens['"]?\s*[:=]\s*(\d+)"#).unwrap(),
+        completion: Regex::new(r#"(?i)\b(?:completion|output)[_ ]tokens['"]?\s*[:=]\s*(\d+)"#).unwrap(),
+        model: Regex::new(r#"(?i)\bmodel['"]?\s*[:=]\s*['"]?([\w./:\-]+)"#).unwrap(),
+        // "session=telegram:12345"、"'session_key': 'discord:42'"
+        session: Regex::new(r#"(?i)\b(?:session(?:_key)?|chat)['"]?\s*[:=]\s*['"]?([\w\-]+):[^\s'",]+"#).unwrap(),
+    })
+}
+
+/// 估算文本的 token 数：ASCII 约 4 个字符一个 token，其他字符（如中文）约一个字符一个 token
+pub fn estimate_tokens(text: &str) -> u64 {
+    let (ascii, other) = text.chars().fold((0u64, 0u64), |(a, o), c| {
+        if c.is_ascii() { (a + 1, o) } else { (a, o + 1) }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 根据模型名推断 provider
+pub fn infer_provider(model: &str) -> String {
+    if let Some((prefix, _)) = model.split_once('/') {
+        if provider::find_provider_spec(prefix).is_some() {
+            return prefix.to_string();
+        }
+    }
+
+    if let Some(spec) = provider::PROVIDERS.iter().find(|p| p.models.contains(&model)) {
+        return spec.id.to_string();
+    }
+
+    let lower = model.to_lowercase();
+    let keywords: &[(&str, &str)] = &[
+        ("claude", "anthropic"),
+        ("gpt", "openai"),
+        ("deepseek", "deepseek"),
+        ("gemini", "gemini"),
+        ("qwen", "dashscope"),
+        ("moonshot", "moonshot"),
+        ("kimi", "moonshot"),
+        ("glm", "zhipu"),
+        ("doubao", "volcengine"),
+        ("minimax", "minimax"),
+    ];
+
+    keywords
+        .iter()
+        .find(|(keyword, _)| lower.contains(keyword))
+        .map(|(_, id)| id.to_string())
+        .unwrap_or_else(|| UNKNOWN.to_string())
+}
+
+/// 配置中的默认模型
+fn default_model() -> String {
+    config::read_config_file()
+        .ok()
+        .and_then(|c| c.pointer("/agents/defaults/model").and_then(|m| m.as_str()).map(str::to_string))
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| UNKNOWN.to_string())
+}
+
+fn entry(
+    timestamp: Option<String>,
+    day: String,
+    model: &str,
+    channel: Option<&str>,
+    tokens: (u64, u64),
+    estimated: bool,
+    source: UsageSource,
+) -> UsageEntry {
+    UsageEntry {
+        day,
+        timestamp,
+        model: model.to_string(),
+        provider: infer_provider(model),
+        channel: channel.unwrap_or(UNKNOWN).to_string(),
+        prompt_tokens: tokens.0,
+        completion_tokens: tokens.1,
+        estimated,
+        source,
+    }
+}
+
+/// 从日志记录中提取 token 数
+/// 渠道取自同一行中的会话标识（channel:chat_id），没有时记为 unknown：
+/// 多个渠道同时活跃时，按最近收到的消息推断会把用量算到错误的渠道上
+fn log_usage(record: &LogRecord, default_model: &str) -> Option<UsageEntry> {
+    let p = patterns();
+    let count = |regex: &Regex| -> Option<u64> { regex.captures(&record.message)?[1].parse().ok() };
+
+    let prompt = count(&p.prompt);
+    let completion = count(&p.completion);
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+
+    let day = record.timestamp.as_deref()?.get(..10)?.to_string();
+    let model = p
+        .model
+        .captures(&record.message)
+        .map(|caps| caps[1].to_string())
+        .unwrap_or_else(|| default_model.to_string());
+    let channel = p.session.captures(&record.message).map(|caps| caps[1].to_string());
+
+    Some(entry(
+        record.timestamp.clone(),
+        day,
+        &model,
+        channel.as_deref(),
+        (prompt.unwrap_or(0), completion.unwrap_or(0)),
+        false,
+        UsageSource::Log,
+    ))
+}
+
+/// 从日志文件中提取用量，files 按从新到旧排列
+pub fn collect_log_usage(files: &[LogFile], default_model: &str) -> Result<Vec<UsageEntry>, String> {
+    let mut entries = Vec::new();
+
+    for file in files.iter().rev() {
+        let limit = if file.current { file.size } else { u64::MAX };
+
+        logfiles::read_records(file, limit, |record| {
+            entries.extend(log_usage(&record, default_model));
+            Ok(())
+        })?;
+    }
+
+    Ok(entries)
+}
+
+/// 消息的文本内容（字符串或多段内容）
+fn message_text(message: &JsonValue) -> String {
+    let mut text = match message.get("content") {
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(JsonValue::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+
+    if let Some(tool_calls) = message.get("tool_calls") {
+        text.push_str(&tool_calls.to_string());
+    }
+
+    text
+}
+
+/// 消息中记录的 token 数
+fn message_usage(message: &JsonValue) -> Option<(u64, u64)> {
+    let usage = message.get("usage")?;
+    let field = |names: [&str; 2]| names.iter().find_map(|n| usage.get(*n).and_then(|v| v.as_u64()));
+
+    let prompt = field(["prompt_tokens", "input_tokens"]);
+    let completion = field(["completion_tokens", "output_tokens"]);
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+
+    Some((prompt.unwrap_or(0), completion.unwrap_or(0)))
+}
+
+/// 从一个会话文件中提取用量：每条 assistant 消息算一次请求，
+/// 没有 usage 字段时以之前的消息（最近 CONTEXT_MESSAGES 条）估算输入，以回复内容估算输出
+fn session_usage(path: &Path, default_model: &str) -> Vec<UsageEntry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    // 会话文件名为 <channel>_<chat_id>.jsonl
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let channel = stem.split_once('_').map(|(c, _)| c.to_string());
+
+    let fallback_day = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+
+    let mut entries = Vec::new();
+    let mut context: VecDeque<u64> = VecDeque::new();
+
+    for line in content.lines() {
+        let Ok(message) = serde_json::from_str::<JsonValue>(line) else {
+            continue;
+        };
+        let Some(role) = message.get("role").and_then(|r| r.as_str()) else {
+            continue;
+        };
+
+        let tokens = estimate_tokens(&message_text(&message)) + MESSAGE_OVERHEAD;
+
+        if role == "assistant" {
+            let timestamp = message.get("timestamp").and_then(|t| t.as_str()).map(str::to_string);
+            let day = timestamp
+                .as_deref()
+                .and_then(|t| t.get(..10))
+                .map(str::to_string)
+                .unwrap_or_else(|| fallback_day.clone());
+            let model = message.get("model").and_then(|m| m.as_str()).unwrap_or(default_model);
+
+            let (usage, estimated) = match message_usage(&message) {
+                Some(usage) => (usage, false),
+                None => ((context.iter().sum(), tokens), true),
+            };
+
+            entries.push(entry(timestamp, day, model, channel.as_deref(), usage, estimated, UsageSource::Session));
+        }
+
+        if context.len() >= CONTEXT_MESSAGES {
+            context.pop_front();
+        }
+        context.push_back(tokens);
+    }
+
+    entries
+}
+
+/// 从会话目录中提取用量
+pub fn collect_session_usage(dir: &Path, default_model: &str) -> Vec<UsageEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().map(|e| e == "jsonl").unwrap_or(false))
+        .collect();
+    paths.sort();
+
+    paths.iter().flat_map(|p| session_usage(p, default_model)).collect()
+}
+
+/// 解析日志（2025-01-01 10:00:00.000）或会话（2025-01-01T10:00:00.123456）中的本地时间
+fn parse_timestamp(timestamp: Option<&str>) -> Option<chrono::NaiveDateTime> {
+    let timestamp = timestamp?.replacen('T', " ", 1);
+    chrono::NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+/// 日志记录的渠道未知时可以对应任何渠道的会话
+fn same_channel(log: &UsageEntry, session: &UsageEntry) -> bool {
+    log.channel == UNKNOWN || log.channel == session.channel
+}
+
+/// 合并日志和会话中的用量，逐个请求去重
+/// 会话中的回复在同一渠道、MATCH_WINDOW_SECS 内有尚未匹配的日志记录时视为同一请求，取最接近的一条并丢弃会话数据；
+/// 没有时间戳的会话回复无法逐个匹配，只在当天该渠道有日志记录时丢弃
+pub fn merge_usage(log: Vec<UsageEntry>, session: Vec<UsageEntry>) -> Vec<UsageEntry> {
+    let mut log_by_day: HashMap<&str, Vec<(usize, Option<chrono::NaiveDateTime>)>> = HashMap::new();
+    for (i, entry) in log.iter().enumerate() {
+        log_by_day
+            .entry(entry.day.as_str())
+            .or_default()
+            .push((i, parse_timestamp(entry.timestamp.as_deref())));
+    }
+
+    let mut matched = vec![false; log.len()];
+    let mut kept = Vec::new();
+
+    for entry in session {
+        let candidates = log_by_day.get(entry.day.as_str()).map(Vec::as_slice).unwrap_or_default();
+
+        let duplicate = match parse_timestamp(entry.timestamp.as_deref()) {
+            Some(time) => {
+                let closest = candidates
+                    .iter()
+                    .filter(|(i, _)| !matched[*i] && same_channel(&log[*i], &entry))
+                    .filter_map(|(i, log_time)| Some((*i, (*log_time)?.signed_duration_since(time).num_seconds().abs())))
+                    .filter(|(_, diff)| *diff <= MATCH_WINDOW_SECS)
+                    .min_by_key(|(_, diff)| *diff);
+
+                match closest {
+                    Some((i, _)) => {
+                        matched[i] = true;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            None => candidates.iter().any(|(i, _)| same_channel(&log[*i], &entry)),
+        };
+
+        if !duplicate {
+            kept.push(entry);
+        }
+    }
+
+    log.into_iter().chain(kept).collect()
+}
+
+/// 按维度汇总
+pub fn aggregate(entries: &[UsageEntry], query: &UsageQuery, prices: &UsagePriceTable) -> UsageReport {
+    let dimensions = query
+        .group_by
+        .clone()
+        .unwrap_or_else(|| vec![UsageDimension::Day, UsageDimension::Model]);
+    let include_estimates = query.include_estimates.unwrap_or(true);
+    let since = query.since.as_deref().filter(|s| !s.is_empty());
+    let until = query.until.as_deref().filter(|s| !s.is_empty());
+    let matcher = prices.compile();
+
+    let mut rows: BTreeMap<[Option<String>; 4], UsageRow> = BTreeMap::new();
+    let mut totals = UsageRow::default();
+    let mut unpriced = HashSet::new();
+
+    for entry in entries {
+        if !include_estimates && entry.estimated {
+            continue;
+        }
+        if since.map(|s| entry.day.as_str() < s).unwrap_or(false) {
+            continue;
+        }
+        if until.map(|u| entry.day.as_str() > u).unwrap_or(false) {
+            continue;
+        }
+
+        let key_of = |dimension: UsageDimension, value: &str| {
+            dimensions.contains(&dimension).then(|| value.to_string())
+        };
+        let key = [
+            key_of(UsageDimension::Day, &entry.day),
+            key_of(UsageDimension::Model, &entry.model),
+            key_of(UsageDimension::Provider, &entry.provider),
+            key_of(UsageDimension::Channel, &entry.channel),
+        ];
+
+        let cost = matcher.cost(entry);
+        if cost.is_none() {
+            unpriced.insert(entry.model.clone());
+        }
+
+        rows.entry(key.clone())
+            .or_insert_with(|| {
+                let [day, model, provider, channel] = key;
+                UsageRow { day, model, provider, channel, ..Default::default() }
+            })
+            .add(entry, cost);
+        totals.add(entry, cost);
+    }
+
+    let mut rows: Vec<UsageRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| b.day.cmp(&a.day).then(b.total_tokens.cmp(&a.total_tokens)));
+
+    let mut unpriced_models: Vec<String> = unpriced.into_iter().collect();
+    unpriced_models.sort();
+
+    UsageReport {
+        rows,
+        totals,
+        currency: prices.currency.clone(),
+        unpriced_models,
+    }
+}
+
+/// 获取价格表文件路径
+fn get_prices_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".nanobot").join("usage_prices.json")
+}
+
+/// 加载价格表，文件不存在或无法解析时使用默认值
+pub fn load_prices() -> UsagePriceTable {
+    fs::read_to_string(get_prices_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存价格表
+fn save_prices(prices: &UsagePriceTable) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(prices)
+        .map_err(|e| format!("序列化价格表失败: {}", e))?;
+
+    fsutil::atomic_write(&get_prices_path(), content)
+        .map_err(|e| format!("保存价格表失败: {}", e))
+}
+
+/// 读取日志和会话文件并汇总
+fn build_report(query: &UsageQuery) -> Result<UsageReport, String> {
+    let model = default_model();
+    let files = logfiles::discover_log_files()?;
+    let log = collect_log_usage(&files, &model)?;
+
+    let session = session::get_chat_sessions_path()
+        .map(|dir| collect_session_usage(&dir, &model))
+        .unwrap_or_default();
+
+    Ok(aggregate(&merge_usage(log, session), query, &load_prices()))
+}
+
+/// 汇总结果转为 CSV
+fn report_csv(report: &UsageReport) -> String {
+    let mut out = String::from(
+        "day,model,provider,channel,requests,estimatedRequests,promptTokens,completionTokens,totalTokens,cost,currency\n",
+    );
+
+    for row in &report.rows {
+        let fields = [
+            row.day.clone().unwrap_or_default(),
+            row.model.clone().unwrap_or_default(),
+            row.provider.clone().unwrap_or_default(),
+            row.channel.clone().unwrap_or_default(),
+            row.requests.to_string(),
+            row.estimated_requests.to_string(),
+            row.prompt_tokens.to_string(),
+            row.completion_tokens.to_string(),
+            row.total_tokens.to_string(),
+            format!("{:.6}", row.cost),
+            report.currency.clone(),
+        ];
+        let line: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// 获取价格表
+#[tauri::command]
+pub async fn get_usage_prices() -> Result<UsagePriceTable, String> {
+    Ok(load_prices())
+}
+
+/// 保存价格表
+#[tauri::command]
+pub async fn set_usage_prices(prices: UsagePriceTable) -> Result<UsagePriceTable, String> {
+    prices.validate()?;
+    save_prices(&prices)?;
+    Ok(prices)
+}
+
+/// 查询 token 用量和估算费用
+#[tauri::command]
+pub async fn query_usage(query: Option<UsageQuery>) -> Result<UsageReport, String> {
+    let query = query.unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || build_report(&query))
+        .await
+        .map_err(|e| format!("统计用量失败: {}", e))?
+}
+
+/// 按查询条件把用量汇总导出为 CSV
+#[tauri::command]
+pub async fn export_usage_csv(query: Option<UsageQuery>, path: String) -> Result<UsageExportResult, String> {
+    let target = PathBuf::from(path.trim());
+    if target.as_os_str().is_empty() {
+        return Err("导出路径不能为空".to_string());
+    }
+
+    let query = query.unwrap_or_default();
+    let report = tokio::task::spawn_blocking(move || build_report(&query))
+        .await
+        .map_err(|e| format!("统计用量失败: {}", e))??;
+
+    let csv = report_csv(&report);
+    fsutil::atomic_write(&target, &csv)
+        .map_err(|e| format!("保存导出文件失败: {}", e))?;
+
+    Ok(UsageExportResult {
+        path: target.to_string_lossy().to_string(),
+        rows: report.rows.len(),
+        bytes: csv.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(timestamp: &str, channel: &str, tokens: (u64, u64), estimated: bool, source: UsageSource) -> UsageEntry {
+        let timestamp = (!timestamp.is_empty()).then(|| timestamp.to_string());
+        let day = timestamp.as_deref().map(|t| t[..10].to_string()).unwrap_or_else(|| "2025-01-01".to_string());
+        entry(timestamp, day, "gpt-4o", Some(channel), tokens, estimated, source)
+    }
+
+    fn log(timestamp: &str, channel: &str, tokens: (u64, u64)) -> UsageEntry {
+        usage(timestamp, channel, tokens, false, UsageSource::Log)
+    }
+
+    fn session(timestamp: &str, channel: &str, tokens: (u64, u64), estimated: bool) -> UsageEntry {
+        usage(timestamp, channel, tokens, estimated, UsageSource::Session)
+    }
+
+    fn tokens(entries: &[UsageEntry]) -> Vec<(UsageSource, u64)> {
+        entries.iter().map(|e| (e.source, e.prompt_tokens)).collect()
+    }
+
+    #[test]
+    fn merge_drops_only_the_matching_session_reply() {
+        let merged = merge_usage(
+            vec![log("2025-01-01 10:00:05.000", "telegram", (100, 10))],
+            vec![
+                // 与日志记录是同一请求
+                session("2025-01-01T10:00:07.123456", "telegram", (90, 9), true),
+                // 同一天的其他请求只记录在会话中
+                session("2025-01-01T15:00:00", "telegram", (50, 5), true),
+                session("2025-01-01T10:00:06", "discord", (30, 3), false),
+            ],
+        );
+
+        assert_eq!(
+            tokens(&merged),
+            vec![(UsageSource::Log, 100), (UsageSource::Session, 50), (UsageSource::Session, 30)]
+        );
+    }
+
+    #[test]
+    fn each_log_record_matches_one_session_reply() {
+        let merged = merge_usage(
+            vec![
+                log("2025-01-01 10:00:00.000", "unknown", (100, 10)),
+                log("2025-01-01 10:01:00.000", "unknown", (200, 20)),
+            ],
+            vec![
+                session("2025-01-01T10:00:58", "telegram", (1, 1), true),
+                session("2025-01-01T10:00:59", "telegram", (2, 2), true),
+                session("2025-01-01T10:01:01", "telegram", (3, 3), true),
+            ],
+        );
+
+        // 渠道未知的日志记录可以对应任何渠道，每条只抵消一条会话回复
+        assert_eq!(tokens(&merged), vec![(UsageSource::Log, 100), (UsageSource::Log, 200), (UsageSource::Session, 3)]);
+    }
+
+    #[test]
+    fn session_reply_without_timestamp_dropped_only_for_logged_channel() {
+        let merged = merge_usage(
+            vec![log("2025-01-01 10:00:00.000", "telegram", (100, 10))],
+            vec![session("", "telegram", (1, 1), true), session("", "discord", (2, 2), true)],
+        );
+
+        assert_eq!(tokens(&merged), vec![(UsageSource::Log, 100), (UsageSource::Session, 2)]);
+    }
+
+    #[test]
+    fn aggregate_groups_filters_and_prices() {
+        let entries = merge_usage(
+            vec![log("2025-01-02 10:00:00.000", "telegram", (1_000_000, 100_000))],
+            vec![
+                session("2025-01-02T10:00:01", "telegram", (999, 99), true),
+                session("2025-01-01T09:00:00", "discord", (1_000, 500), true),
+            ],
+        );
+        let prices = UsagePriceTable::default();
+
+        let report = aggregate(&entries, &UsageQuery::default(), &prices);
+        assert_eq!(report.totals.requests, 2);
+        assert_eq!(report.totals.estimated_requests, 1);
+        assert_eq!(report.rows.len(), 2);
+        // 按日期从新到旧
+        assert_eq!(report.rows[0].day.as_deref(), Some("2025-01-02"));
+        assert_eq!(report.rows[0].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(report.rows[0].channel, None);
+        assert!((report.rows[0].cost - 3.5).abs() < 1e-9);
+
+        let query = UsageQuery {
+            group_by: Some(vec![UsageDimension::Channel]),
+            include_estimates: Some(false),
+            ..Default::default()
+        };
+        let report = aggregate(&entries, &query, &prices);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].channel.as_deref(), Some("telegram"));
+        assert_eq!(report.rows[0].total_tokens, 1_100_000);
+
+        let query = UsageQuery {
+            since: Some("2025-01-02".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(aggregate(&entries, &query, &prices).totals.requests, 1);
+
+        let no_prices = UsagePriceTable {
+            prices: Vec::new(),
+            ..Default::default()
+        };
+        let report = aggregate(&entries, &UsageQuery::default(), &no_prices);
+        assert_eq!(report.unpriced_models, vec!["gpt-4o"]);
+        assert_eq!(report.totals.unpriced_requests, 2);
+    }
+}
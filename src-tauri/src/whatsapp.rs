@@ -0,0 +1,537 @@
+// WhatsApp 登录模块
+// 运行 `nanobot channels login` 子进程，从输出中解析二维码内容并渲染为 PNG，
+// 同时跟踪登录状态（waiting / scanned / connected / failed）
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc;
+
+use crate::process;
+
+/// 渲染 PNG 时每个模块的像素数
+const QR_MODULE_PIXELS: u32 = 8;
+
+/// 二维码四周的空白模块数
+const QR_QUIET_ZONE: u32 = 4;
+
+/// 状态消息保留的输出行数
+const OUTPUT_TAIL_LINES: usize = 50;
+
+/// 登录状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhatsAppLoginStatus {
+    /// idle / waiting / scanned / connected / failed
+    pub state: String,
+    /// 二维码原始内容
+    pub qr: Option<String>,
+    /// 二维码 PNG（base64）
+    pub qr_png: Option<String>,
+    /// 无法解析原始内容时，保留终端输出的字符画二维码
+    pub qr_ascii: Option<String>,
+    pub message: Option<String>,
+    pub started_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    /// 子进程最近的输出
+    pub output: VecDeque<String>,
+}
+
+/// WhatsApp 登录状态管理
+pub struct WhatsAppLoginState {
+    status: Arc<Mutex<WhatsAppLoginStatus>>,
+    task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl WhatsAppLoginState {
+    pub fn new() -> Self {
+        Self {
+            status: Arc::new(Mutex::new(WhatsAppLoginStatus {
+                state: "idle".to_string(),
+                ..Default::default()
+            })),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> WhatsAppLoginStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// 启动登录子进程，已有登录进程时先终止
+    /// notify 在每次状态变化时调用
+    pub fn start<F>(&self, program: &str, args: &[String], notify: F) -> Result<WhatsAppLoginStatus, String>
+    where
+        F: Fn(&WhatsAppLoginStatus) + Send + 'static,
+    {
+        self.cancel();
+
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.args(args)
+            .env("PYTHONUTF8", "1")
+            .env("PYTHONIOENCODING", "utf-8")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let child = cmd
+            .spawn()
+            .map_err(|e| format!("启动 WhatsApp 登录进程失败: {}", e))?;
+
+        let now = chrono::Utc::now().timestamp();
+        let initial = WhatsAppLoginStatus {
+            state: "waiting".to_string(),
+            message: Some("等待二维码...".to_string()),
+            started_at: Some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+
+        *self.status.lock().unwrap() = initial.clone();
+        notify(&initial);
+
+        let status = self.status.clone();
+        let task = tokio::spawn(run_login(child, status, notify));
+        *self.task.lock().unwrap() = Some(task);
+
+        Ok(initial)
+    }
+
+    /// 终止登录子进程（kill_on_drop 会在任务取消时结束进程）
+    pub fn cancel(&self) -> bool {
+        let Some(task) = self.task.lock().unwrap().take() else {
+            return false;
+        };
+
+        let running = !task.is_finished();
+        task.abort();
+
+        if running {
+            let mut status = self.status.lock().unwrap();
+            status.state = "idle".to_string();
+            status.message = Some("登录已取消".to_string());
+            status.updated_at = Some(chrono::Utc::now().timestamp());
+        }
+
+        running
+    }
+}
+
+/// 从单行输出中解析二维码内容
+/// 支持 JSON（{"type":"qr","qr":"..."}）、`QR: <内容>` 前缀和 Baileys 原始格式（`2@...,...,...`）
+pub fn parse_qr_payload(line: &str) -> Option<String> {
+    let line = line.trim();
+
+    if line.starts_with('{') {
+        if let Ok(value) = serde_json::from_str::<JsonValue>(line) {
+            return value
+                .get("qr")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string());
+        }
+    }
+
+    let lower = line.to_lowercase();
+    for prefix in ["qr code:", "qr:"] {
+        if lower.starts_with(prefix) {
+            let payload = line[prefix.len()..].trim();
+            if !payload.is_empty() && !payload.contains(char::is_whitespace) {
+                return Some(payload.to_string());
+            }
+        }
+    }
+
+    // Baileys 的配对内容形如 "2@ref,noiseKey,identityKey,advSecret"
+    if let Some((head, rest)) = line.split_once('@') {
+        if !head.is_empty()
+            && head.chars().all(|c| c.is_ascii_digit())
+            && rest.matches(',').count() >= 3
+            && !line.contains(char::is_whitespace)
+        {
+            return Some(line.to_string());
+        }
+    }
+
+    None
+}
+
+/// 判断是否为终端字符画二维码的一行
+fn is_ascii_qr_line(line: &str) -> bool {
+    let line = line.trim_end();
+    !line.is_empty()
+        && line.chars().any(|c| matches!(c, '█' | '▀' | '▄'))
+        && line.chars().all(|c| matches!(c, '█' | '▀' | '▄' | ' '))
+}
+
+/// 根据输出推断登录状态，返回 None 表示该行不改变状态
+fn detect_state(line: &str) -> Option<&'static str> {
+    let lower = line.to_lowercase();
+
+    if lower.contains("logged out") || lower.contains("login failed") || lower.contains("timed out") {
+        return Some("failed");
+    }
+
+    if lower.contains("disconnect") || lower.contains("not connected") {
+        return None;
+    }
+
+    if lower.contains("connected") || lower.contains("logged in") || lower.contains("login successful") {
+        return Some("connected");
+    }
+
+    if lower.contains("scanned") || lower.contains("pairing") || lower.contains("authenticating") {
+        return Some("scanned");
+    }
+
+    None
+}
+
+/// 将二维码内容渲染为 PNG（base64）
+pub fn render_qr_png(payload: &str) -> Result<String, String> {
+    let code = qrcode::QrCode::new(payload.as_bytes())
+        .map_err(|e| format!("生成二维码失败: {}", e))?;
+
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (modules + QR_QUIET_ZONE * 2) * QR_MODULE_PIXELS;
+
+    let mut pixels = vec![255u8; (size * size) as usize];
+    for y in 0..size {
+        for x in 0..size {
+            let mx = (x / QR_MODULE_PIXELS).checked_sub(QR_QUIET_ZONE);
+            let my = (y / QR_MODULE_PIXELS).checked_sub(QR_QUIET_ZONE);
+            if let (Some(mx), Some(my)) = (mx, my) {
+                if mx < modules && my < modules && colors[(my * modules + mx) as usize] == qrcode::Color::Dark {
+                    pixels[(y * size + x) as usize] = 0;
+                }
+            }
+        }
+    }
+
+    let mut buf = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buf, size, size);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("编码 PNG 失败: {}", e))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| format!("编码 PNG 失败: {}", e))?;
+    }
+
+    Ok(BASE64.encode(buf))
+}
+
+/// 逐行转发子进程输出
+fn forward_lines<R>(reader: R, tx: mpsc::UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// 处理登录子进程的输出直到退出
+async fn run_login<F>(mut child: tokio::process::Child, status: Arc<Mutex<WhatsAppLoginStatus>>, notify: F)
+where
+    F: Fn(&WhatsAppLoginStatus) + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_lines(stderr, tx.clone());
+    }
+    drop(tx);
+
+    let mut ascii_block: Vec<String> = Vec::new();
+
+    while let Some(line) = rx.recv().await {
+        let mut status = status.lock().unwrap();
+        let mut changed = false;
+
+        if is_ascii_qr_line(&line) {
+            ascii_block.push(line);
+            continue;
+        }
+
+        // 字符画结束：只有在没有原始内容时才使用
+        if !ascii_block.is_empty() {
+            if ascii_block.len() >= 10 && status.qr.is_none() {
+                status.qr_ascii = Some(ascii_block.join("\n"));
+                status.message = Some("请使用 WhatsApp 扫描二维码".to_string());
+                changed = true;
+            }
+            ascii_block.clear();
+        }
+
+        if status.output.len() >= OUTPUT_TAIL_LINES {
+            status.output.pop_front();
+        }
+        status.output.push_back(line.clone());
+
+        if let Some(payload) = parse_qr_payload(&line) {
+            // 二维码会定期刷新，始终使用最新的内容
+            if status.qr.as_deref() != Some(payload.as_str()) {
+                status.qr_png = render_qr_png(&payload).ok();
+                status.qr = Some(payload);
+                status.qr_ascii = None;
+                status.state = "waiting".to_string();
+                status.message = Some("请使用 WhatsApp 扫描二维码".to_string());
+                changed = true;
+            }
+        } else if let Some(state) = detect_state(&line) {
+            if status.state != state {
+                status.state = state.to_string();
+                status.message = Some(line.trim().to_string());
+                if state == "connected" {
+                    status.qr = None;
+                    status.qr_png = None;
+                    status.qr_ascii = None;
+                }
+                changed = true;
+            }
+        }
+
+        if changed {
+            status.updated_at = Some(chrono::Utc::now().timestamp());
+            notify(&status);
+        }
+    }
+
+    let exit = child.wait().await;
+
+    let mut status = status.lock().unwrap();
+    let code = exit.as_ref().ok().and_then(|s| s.code());
+
+    // 正常退出时保留已连接的状态；非零退出码、等待失败或在连接前被终止才算失败
+    let connected = status.state == "connected";
+    let (state, message) = match (&exit, code) {
+        (Ok(_), Some(0)) if connected => ("connected", "登录完成，登录进程已退出".to_string()),
+        (Ok(_), Some(0)) => ("idle", "登录进程已退出，未完成登录".to_string()),
+        (Ok(_), Some(code)) => ("failed", format!("登录进程已退出（退出码 {}）", code)),
+        (Ok(_), None) if connected => ("idle", "登录进程已被终止".to_string()),
+        (Ok(_), None) => ("failed", "登录进程已被终止".to_string()),
+        (Err(e), _) => ("failed", format!("等待登录进程失败: {}", e)),
+    };
+    status.state = state.to_string();
+    status.message = Some(message);
+    status.qr = None;
+    status.qr_png = None;
+    status.qr_ascii = None;
+    status.updated_at = Some(chrono::Utc::now().timestamp());
+    notify(&status);
+}
+
+/// 开始 WhatsApp 登录
+/// 状态变化通过 whatsapp-login 事件推送，也可通过 get_whatsapp_login_status 轮询
+#[tauri::command]
+pub async fn start_whatsapp_login(
+    window: tauri::Window,
+    state: State<'_, WhatsAppLoginState>,
+) -> Result<WhatsAppLoginStatus, String> {
+    let (program, _, mut args) = process::find_nanobot_command()
+        .ok_or_else(|| "未找到 nanobot 命令，请先安装 nanobot-ai 或配置正确的 Python 路径".to_string())?;
+
+    args.push("channels".to_string());
+    args.push("login".to_string());
+
+    state.start(&program, &args, move |status| {
+        let _ = window.emit("whatsapp-login", status);
+    })
+}
+
+/// 获取 WhatsApp 登录状态
+#[tauri::command]
+pub async fn get_whatsapp_login_status(
+    state: State<'_, WhatsAppLoginState>,
+) -> Result<WhatsAppLoginStatus, String> {
+    Ok(state.status())
+}
+
+/// 取消 WhatsApp 登录（同时停止 bridge）
+#[tauri::command]
+pub async fn cancel_whatsapp_login(
+    state: State<'_, WhatsAppLoginState>,
+) -> Result<WhatsAppLoginStatus, String> {
+    state.cancel();
+    Ok(state.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_qr_payload_formats() {
+        assert_eq!(parse_qr_payload(r#"{"type":"qr","qr":"2@abc"}"#).as_deref(), Some("2@abc"));
+        assert_eq!(parse_qr_payload(r#"{"type":"qr","qr":""}"#), None);
+        assert_eq!(parse_qr_payload("QR: 2@abc,def").as_deref(), Some("2@abc,def"));
+        assert_eq!(parse_qr_payload("qr code: ref123").as_deref(), Some("ref123"));
+        assert_eq!(parse_qr_payload("QR: scan this with your phone"), None);
+        assert_eq!(parse_qr_payload("  2@ref,noise,identity,secret  ").as_deref(), Some("2@ref,noise,identity,secret"));
+        assert_eq!(parse_qr_payload("user@example.com, a, b, c"), None);
+        assert_eq!(parse_qr_payload("2@only,two"), None);
+        assert_eq!(parse_qr_payload("hello, world"), None);
+    }
+
+    #[test]
+    fn renders_qr_as_png() {
+        let png = BASE64.decode(render_qr_png("2@abc,def,ghi,jkl").unwrap()).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    fn python() -> Option<String> {
+        process::find_command("python3")
+    }
+
+    /// 运行脚本化的登录进程，收集状态变化直到进程退出
+    async fn run_script(script: &str) -> (Vec<WhatsAppLoginStatus>, WhatsAppLoginStatus) {
+        let python = python().unwrap();
+        let state = WhatsAppLoginState::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        state
+            .start(&python, &["-c".to_string(), script.to_string()], move |status| {
+                let _ = tx.send(status.clone());
+            })
+            .unwrap();
+
+        // 登录任务结束后发送端随之释放
+        let mut seen = Vec::new();
+        while let Some(status) = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("登录进程没有退出")
+        {
+            seen.push(status);
+        }
+
+        (seen, state.status())
+    }
+
+    #[tokio::test]
+    async fn login_follows_process_output() {
+        if python().is_none() {
+            return;
+        }
+
+        let script = r#"
+import sys
+print("Starting WhatsApp bridge...", flush=True)
+print("QR: 2@abc,def,ghi,jkl", flush=True)
+print("QR: 2@abc,def,ghi,jkl", flush=True)
+print('{"type":"qr","qr":"2@new,def,ghi,jkl"}', flush=True)
+print("Pairing device...", flush=True)
+print("Connected to WhatsApp", flush=True)
+sys.exit(3)
+"#;
+        let (seen, last) = run_script(script).await;
+
+        let states: Vec<&str> = seen.iter().map(|s| s.state.as_str()).collect();
+        assert_eq!(states, vec!["waiting", "waiting", "waiting", "scanned", "connected", "failed"]);
+
+        // 重复的二维码不会触发更新，刷新后的二维码会替换旧的
+        assert_eq!(seen[1].qr.as_deref(), Some("2@abc,def,ghi,jkl"));
+        assert!(seen[1].qr_png.is_some());
+        assert_eq!(seen[2].qr.as_deref(), Some("2@new,def,ghi,jkl"));
+        assert_eq!(seen[4].qr, None);
+
+        assert_eq!(last.state, "failed");
+        assert!(last.message.unwrap().contains("3"));
+        assert_eq!(last.output.back().map(|s| s.as_str()), Some("Connected to WhatsApp"));
+    }
+
+    #[tokio::test]
+    async fn clean_exit_keeps_connected_state() {
+        if python().is_none() {
+            return;
+        }
+
+        let script = r#"
+print("QR: 2@abc,def,ghi,jkl", flush=True)
+print("Connected to WhatsApp", flush=True)
+"#;
+        let (seen, last) = run_script(script).await;
+
+        let states: Vec<&str> = seen.iter().map(|s| s.state.as_str()).collect();
+        assert_eq!(states, vec!["waiting", "waiting", "connected", "connected"]);
+        assert_eq!(last.state, "connected");
+        assert_eq!(last.qr, None);
+    }
+
+    #[tokio::test]
+    async fn clean_exit_before_connecting_is_not_failure() {
+        if python().is_none() {
+            return;
+        }
+
+        let (_, last) = run_script(r#"print("QR: 2@abc,def,ghi,jkl", flush=True)"#).await;
+        assert_eq!(last.state, "idle");
+        assert_eq!(last.qr, None);
+        assert!(last.message.unwrap().contains("未完成登录"));
+    }
+
+    #[tokio::test]
+    async fn login_keeps_ascii_qr_and_output_tail() {
+        if python().is_none() {
+            return;
+        }
+
+        let script = r#"
+for i in range(60):
+    print("line %d" % i, flush=True)
+for _ in range(12):
+    print("██▀▀▄▄  ██", flush=True)
+print("Scan the QR code above", flush=True)
+"#;
+        let (seen, last) = run_script(script).await;
+
+        let ascii = seen.iter().find_map(|s| s.qr_ascii.clone()).expect("没有识别字符画二维码");
+        assert_eq!(ascii.lines().count(), 12);
+
+        assert_eq!(last.output.len(), OUTPUT_TAIL_LINES);
+        assert_eq!(last.output.front().map(|s| s.as_str()), Some("line 11"));
+        assert_eq!(last.output.back().map(|s| s.as_str()), Some("Scan the QR code above"));
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_running_login() {
+        let Some(python) = python() else {
+            return;
+        };
+
+        let state = WhatsAppLoginState::new();
+        state
+            .start(&python, &["-c".to_string(), "import time; time.sleep(30)".to_string()], |_| {})
+            .unwrap();
+
+        assert!(state.cancel());
+        assert_eq!(state.status().state, "idle");
+        assert!(!state.cancel());
+    }
+}